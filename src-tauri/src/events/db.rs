@@ -1,18 +1,43 @@
-use mongodb::{Client, Database};
+use mongodb::Client;
+
+use crate::tools::{
+    settings::{Settings, StorageKind},
+    storage::{embedded::EmbeddedStorage, mongo, store::Store},
+};
 
 pub struct DatabseConnection;
 
 impl DatabseConnection {
-    pub async fn connect<'a>() -> Result<Database, &'a str> {
-        let uri = "mongodb://localhost:27017";
-        let connection = Client::with_uri_str(uri).await;
+    //connect to the storage that is selected in settings
+    pub async fn connect<'a>() -> Result<Store, &'a str> {
+        let settings = Settings::load();
+        match settings.storage {
+            StorageKind::MongoDB => {
+                let connection = Client::with_uri_str(&settings.mongodb_uri).await;
 
-        match connection {
-            Ok(client) => {
-                let db = client.database("Centichain");
-                Ok(db)
+                match connection {
+                    Ok(client) => {
+                        let db = client.database("Centichain");
+                        //a commit that was interrupted is completed and utxos of former versions
+                        //are moved into the utxo set before using the database
+                        match mongo::create_indexes(&db).await {
+                            Ok(_) => match mongo::recover(&db).await {
+                                Ok(_) => match mongo::migrate(&db).await {
+                                    Ok(_) => Ok(Store::MongoDB(db)),
+                                    Err(e) => Err(e),
+                                },
+                                Err(e) => Err(e),
+                            },
+                            Err(e) => Err(e),
+                        }
+                    }
+                    Err(_) => Err("Database Connection Problem-(db-12)"),
+                }
             }
-            Err(_) => Err("Database Connection Problem-(db-12)"),
+            StorageKind::Embedded => match EmbeddedStorage::open(&settings.data_dir) {
+                Ok(db) => Ok(Store::Embedded(db)),
+                Err(e) => Err(e),
+            },
         }
     }
 }
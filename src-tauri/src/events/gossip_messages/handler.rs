@@ -1,22 +1,16 @@
-use libp2p::{PeerId, Swarm};
-use mongodb::Database;
+use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
-use sp_core::ed25519::Public;
-use tauri::Emitter;
 
 use crate::{
-    events::syncing::VSync,
+    events::{node::Node, syncing::VSync},
     generator::{
-        block::{block::Block, message::BlockMessage},
-        leader::Leader,
-        relay::Relay,
-        swarm::CentichainBehaviour,
+        block::{attestation::Attestation, message::BlockMessage},
+        membership::Membership,
+        outnode::{Outnode, OutnodeReports},
         transaction::Transaction,
+        vote::Vote,
     },
-    tools::{
-        trun_sync::{Sync, Turn},
-        wrongdoer::WrongDoer,
-    },
+    tools::{evidence::Evidence, storage::Storage, trun_sync::Sync},
 };
 
 use super::transactions::Transactions;
@@ -27,29 +21,25 @@ pub enum GossipMessages {
     BlockMessage(BlockMessage),
     Transaction(Transaction),
     SyncMessage(VSync),
-    LeaderVote(PeerId),
-    Outnode(PeerId),
+    LeaderVote(Vote),
+    Attestation(Attestation),
+    Evidence(Evidence),
+    Membership(Membership),
+    Outnode(Outnode),
 }
 
 impl GossipMessages {
     // Main handler for processing incoming gossip messages
-    pub async fn handle<'a>(
+    pub async fn handle<'n, S: Storage>(
         message: Vec<u8>,
         source: PeerId,
-        swarm: &mut Swarm<CentichainBehaviour>,
-        window: &tauri::Window,
-        db: &'a Database,
-        mempool: &mut Vec<Transaction>,
-        turn: &mut Turn,
-        wallet: &Public,
-        peerid: &PeerId,
-        private: &String,
-        last_block: &mut Vec<Block>,
-        relay: &mut Relay,
-        leader: &mut Leader,
-        sync_state: &mut Sync,
-        recieved_blocks: &mut Vec<BlockMessage>,
-    ) -> Result<(), &'a str> {
+        author: Option<PeerId>,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let db = node.db;
+        let window = node.window;
+        let (peerid, wallet, private) = (node.peerid, node.wallet, node.private);
+
         // Convert message bytes to string
         if let Ok(str_message) = String::from_utf8(message) {
             // Deserialize the message string into a GossipMessages enum
@@ -57,52 +47,37 @@ impl GossipMessages {
                 match gossip_messag {
                     // Handle incoming block messages
                     GossipMessages::BlockMessage(block_message) => {
-                        block_message
-                            .handle(
-                                swarm,
-                                peerid,
-                                db,
-                                recieved_blocks,
-                                sync_state,
-                                last_block,
-                                mempool,
-                                leader,
-                                source,
-                                relay,
-                                window,
-                                turn,
-                                wallet,
-                            )
-                            .await
+                        block_message.handle(source, node).await
                     }
 
                     // Handle incoming transaction messages
                     GossipMessages::Transaction(transaction) => {
                         Transactions::handle(
                             window,
-                            swarm,
+                            node.swarm,
                             transaction,
-                            mempool,
-                            turn,
+                            node.mempool,
+                            node.turn,
                             db,
                             wallet,
                             peerid,
                             private,
-                            last_block,
-                            relay,
+                            node.last_block,
+                            node.relay,
                             source,
-                            leader,
-                            sync_state,
+                            node.leader,
+                            node.sync_state,
+                            node.production,
                         )
                         .await
                     }
 
                     // Handle incoming sync messages
                     GossipMessages::SyncMessage(vsync) => {
-                        match sync_state {
+                        match node.sync_state {
                             Sync::Synced => {
                                 // Add new validator to validators document if it was a correct message
-                                vsync.handle(db, window).await
+                                vsync.handle(db, author, node.last_block, window).await
                             }
                             _ => Ok(()),
                         }
@@ -110,11 +85,13 @@ impl GossipMessages {
 
                     // Handle incoming leader vote messages
                     GossipMessages::LeaderVote(vote) => {
-                        match sync_state {
+                        match node.sync_state {
                             Sync::Synced => {
-                                if leader.in_check {
+                                if node.leader.in_check {
                                     // Process the vote if leader is being checked
-                                    leader.check_votes(db, vote, peerid, turn, window).await
+                                    node.leader
+                                        .check_votes(db, vote, peerid, node.turn, window)
+                                        .await
                                 } else {
                                     Ok(())
                                 }
@@ -123,23 +100,29 @@ impl GossipMessages {
                         }
                     }
 
-                    // Handle incoming outnode messages
-                    GossipMessages::Outnode(outnode) => {
-                        if leader.peerid.is_some() && outnode == leader.peerid.unwrap() {
-                            // Start voting for a new leader if the current leader is the outnode
-                            leader
-                                .start_voting(db, swarm, &peerid, window, turn, sync_state)
-                                .await
-                        } else {
-                            // Remove the outnode from the validators list
-                            match WrongDoer::remove(db, outnode, turn, sync_state, window).await {
-                                Ok(outnode) => Ok(window
-                                    .emit("status", format!("{} is outnode", outnode.to_string()))
-                                    .unwrap()),
-                                Err(e) => Err(e),
-                            }
-                        }
-                    }
+                    // Handle incoming attestations of blocks
+                    GossipMessages::Attestation(attestation) => match node.sync_state {
+                        Sync::Synced => node.finality.handle(attestation, db, window).await,
+                        _ => Ok(()),
+                    },
+
+                    // Handle incoming evidence against wrongdoers
+                    GossipMessages::Evidence(evidence) => match node.sync_state {
+                        Sync::Synced => evidence.handle(node).await,
+                        _ => Ok(()),
+                    },
+
+                    // Handle incoming exit, pause and resume messages of validators
+                    GossipMessages::Membership(membership) => match node.sync_state {
+                        Sync::Synced => membership.handle(author, node).await,
+                        _ => Ok(()),
+                    },
+
+                    // Handle incoming outnode notices of relays
+                    GossipMessages::Outnode(notice) => match node.sync_state {
+                        Sync::Synced => OutnodeReports::handle(notice, node).await,
+                        _ => Ok(()),
+                    },
                 }
             } else {
                 // Return Ok if the message couldn't be deserialized
@@ -1,38 +1,34 @@
-use chrono::Utc;
 use libp2p::{PeerId, Swarm};
-use mongodb::{
-    bson::{doc, from_document, Document},
-    Collection, Database,
-};
-use rust_decimal::Decimal;
 use sp_core::ed25519::Public;
 use tauri::Emitter;
 
 use crate::{
     generator::{
-        block::{block::Block, message::BlockMessage},
+        block::{block::Block, message::BlockMessage, production::Production},
         leader::{Leader, LeaderTime},
+        mempool::Mempool,
         relay::Relay,
         swarm::CentichainBehaviour,
-        transaction::Transaction,
+        transaction::{Conflict, Transaction},
     },
     tools::{
+        changeset::ChangeSet,
+        invalid::Invalid,
+        storage::Storage,
         trun_sync::{Sync, Turn},
-        utxo::Person,
-        wrongdoer::WrongDoer,
     },
 };
 
 pub struct Transactions;
 
 impl Transactions {
-    pub async fn handle<'a>(
+    pub async fn handle<'a, S: Storage>(
         window: &tauri::Window,
         swarm: &mut Swarm<CentichainBehaviour>,
         transaction: Transaction,
-        mempool: &mut Vec<Transaction>,
+        mempool: &mut Mempool,
         turn: &mut Turn,
-        db: &'a Database,
+        db: &'a S,
         wallet: &Public,
         peerid: &PeerId,
         private: &String,
@@ -41,79 +37,59 @@ impl Transactions {
         source: PeerId,
         leader: &mut Leader,
         sync_state: &mut Sync,
+        production: &Production,
     ) -> Result<(), &'a str> {
-        // Validate the transaction
-        match transaction.validate(db).await {
-            Ok(_) => {
-                // Check if the transaction is from the current wallet
-                if transaction.signature[0].key == *wallet {
-                    let sum_centies = Self::sum_centies(db, wallet).await;
-                    match sum_centies {
-                        Ok(sum) => {
-                            // Update the UI with the new balance
-                            window.emit("sum_centies", sum).unwrap();
-                        }
-                        Err(e) => {
-                            return Err(e);
-                        }
-                    }
-                }
+        // Transactions that are in mempool or spend utxos of mempool transactions are refused
+        // they are not wrong doings of the source (relays propagate transactions of users)
+        mempool.expire();
+        if mempool.contains(&transaction.hash) {
+            return Ok(());
+        }
+        if let Some(conflict) = Conflict::in_mempool(&transaction, &mempool.transactions()) {
+            return Ok(window.emit("conflict", conflict).unwrap());
+        }
 
+        // Validate the transaction (its spends are not written, the block that includes it spends the inputs)
+        let mut changes = ChangeSet::new();
+        match transaction.validate(db, &mut changes).await {
+            Ok(_) => {
                 // Add the validated transaction to the mempool
-                mempool.push(transaction);
+                if let Err(e) = mempool.insert(transaction) {
+                    return Ok(window.emit("status", e).unwrap());
+                }
                 // Update the UI with the new mempool state
-                window.emit("mempool", mempool.clone()).unwrap();
+                window.emit("mempool", mempool.transactions()).unwrap();
 
                 // Check if we need to create a new block or change leader
-                if mempool.len() > 1 && !leader.in_check {
+                if !leader.in_check {
                     match leader.timer {
                         LeaderTime::On => {
-                            let now = Utc::now();
-
-                            // Check if the leader's time has expired
-                            if now > leader.time.unwrap() {
-                                // Initiate leader change process
-                                match Leader::start_voting(
-                                    leader, db, swarm, peerid, window, turn, sync_state,
+                            // Initiate leader change process if the leader's time has expired
+                            leader
+                                .check_timeout(
+                                    db, swarm, peerid, wallet, private, window, turn, sync_state,
                                 )
                                 .await
-                                {
-                                    Ok(_) => Ok(()),
-                                    Err(e) => Err(e),
-                                }
-                            } else {
-                                Ok(())
-                            }
                         }
 
                         LeaderTime::Off => {
                             if turn.shift {
-                                // Create a new block with up to 51 transactions from the mempool
-                                let mut transactions = Vec::new();
-                                while transactions.len() < 51 && !mempool.is_empty() {
-                                    transactions.push(mempool.remove(0));
+                                // Wait for more transactions if the production policy doesn't seal the block yet
+                                if !production.should_seal(mempool, last_block) {
+                                    return Ok(());
                                 }
 
-                                // Create and post a new block message
+                                // Create and post a new block message with the highest fee rate transactions of the mempool
                                 match BlockMessage::new(
-                                    db,
-                                    transactions,
-                                    wallet,
-                                    peerid,
-                                    private,
-                                    last_block,
-                                    relay,
-                                    turn,
-                                    leader,
-                                    window,
-                                    sync_state,
+                                    db, mempool, wallet, peerid, private, last_block, relay, turn,
+                                    leader, window, sync_state,
                                 )
                                 .await
                                 {
                                     Ok(block_message) => {
                                         // Update the UI with the new mempool state
-                                        window.emit("mempool", mempool.clone()).unwrap();
-                                        block_message.post(db, swarm, relay).await
+                                        window.emit("mempool", mempool.transactions()).unwrap();
+                                        block_message.post(swarm, relay)
                                     }
                                     Err(e) => Err(e),
                                 }
@@ -128,41 +104,31 @@ impl Transactions {
                     Ok(())
                 }
             }
-            Err(e) => {
+            // A storage error is a problem of this node, not of the transaction
+            Err(Invalid::Storage(e)) => Err(e),
+            Err(Invalid::Rejected(e)) => {
                 // Handle invalid transaction
+                // the source is the peer that forwarded it (usually a relay), so nobody is blamed for it
                 if source == relay.peerid.unwrap() {
                     // Disconnect from the source if it's our relay
                     window.emit("error", e).unwrap();
                     swarm.disconnect_peer_id(source).unwrap();
                     Ok(())
                 } else {
-                    // Remove the wrongdoer from validators
-                    match WrongDoer::remove(db, source, turn, sync_state, window).await {
-                        Ok(wrongdoer) => Ok(window
-                            .emit(
-                                "status",
-                                format!("This wrongdoer removed: {}", wrongdoer.to_string()),
-                            )
-                            .unwrap()),
-                        Err(e) => Err(e),
-                    }
+                    Ok(window.emit("status", e).unwrap())
                 }
             }
         }
     }
 
-    // Calculate the sum of unspent transaction outputs (UTXOs) for a given wallet
-    pub async fn sum_centies<'a>(db: &'a Database, wallet: &Public) -> Result<String, &'a str> {
-        let collection: Collection<Document> = db.collection("UTXOs");
-        let filter = doc! {"wallet": wallet.to_string()};
-        let query = collection.find_one(filter).await;
-        if let Ok(Some(doc)) = query {
-            let person: Person = from_document(doc).unwrap();
-            // Sum up all unspent outputs
-            let sum: Decimal = person.utxos.iter().map(|utxo| utxo.unspent).sum();
-            Ok(sum.to_string())
-        } else {
-            Err("Problem in querying UTXOs-(events/gossip_messages/transactions.rs 141)")
+    // Get the sum of unspent transaction outputs (UTXOs) for a given wallet from its maintained balance
+    pub async fn sum_centies<'a, S: Storage>(
+        db: &'a S,
+        wallet: &Public,
+    ) -> Result<String, &'a str> {
+        match db.balance(wallet).await {
+            Ok(sum) => Ok(sum.to_string()),
+            Err(_) => Err("Problem in querying UTXOs-(events/gossip_messages/transactions.rs 138)"),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::{thread, time::Duration};
+use std::time::Duration;
 
 use libp2p::{
     futures::StreamExt,
@@ -6,47 +6,119 @@ use libp2p::{
     swarm::SwarmEvent,
     PeerId, Swarm,
 };
-use mongodb::Database;
 use sp_core::ed25519::Public;
 use tauri::Emitter;
 
 use crate::{
     generator::{
-        block::{block::Block, message::BlockMessage},
+        block::{
+            attestation::Finality, block::Block, forks::ForkPool, message::BlockMessage,
+            production::Production,
+        },
         leader::Leader,
+        membership::{Action, Membership},
+        mempool::Mempool,
+        outnode::OutnodeReports,
         relay::Relay,
         swarm::{CentichainBehaviour, CentichainBehaviourEvent},
     },
-    tools::trun_sync::{Sync, Turn},
+    tools::{
+        scheduler::{Job, Scheduler},
+        storage::Storage,
+        trun_sync::{Sync, Turn},
+    },
 };
 
 use super::{
-    gossip_messages::handler::GossipMessages, handshaking::Handshake,
+    gossip_messages::handler::GossipMessages, handshaking::Handshake, node::Node,
     outgoing_connection::OutgoingConnection, response::Responses, syncing::VSync,
 };
 
-pub async fn handle(
+// Delay of refreshing UI after handling a response
+const UI_REFRESH_DELAY: Duration = Duration::from_secs(7);
+// Period of checking deadline of the leader
+const LEADER_TIMEOUT_PERIOD: Duration = Duration::from_secs(1);
+// Delay before dialing another relay when connection closed
+const RELAY_REDIAL_DELAY: Duration = Duration::from_secs(3);
+// Period of checking the block production policy
+const BLOCK_PRODUCTION_PERIOD: Duration = Duration::from_secs(1);
+// Period of removing expired transactions of mempool
+const MEMPOOL_EXPIRY_PERIOD: Duration = Duration::from_secs(60);
+// Delay of closing the app after exit message was gossiped
+const SHUTDOWN_DELAY: Duration = Duration::from_secs(2);
+
+pub async fn handle<S: Storage>(
     swarm: &mut Swarm<CentichainBehaviour>,
     window: &tauri::Window,
-    db: &Database,
+    db: &S,
     peerid: &PeerId,
     wallet: &Public,
     private: &String,
 ) {
     // Retrieve relay information from the database
-    match Relay::find(&db).await {
+    match Relay::find(db).await {
         Ok(mut relay) => {
             // Initialize necessary components
             let mut turn = Turn::new();
-            let mut mempool = Vec::new();
+            let mut mempool = Mempool::new();
             let mut recieved_blocks: Vec<BlockMessage> = Vec::new();
             let mut last_block: Vec<Block> = Vec::new();
             let mut sync_state = Sync::new();
             let mut leader = Leader::new(None, window);
+            let production = Production::new();
+            let mut forks = ForkPool::new();
+            let mut finality = Finality::new();
+            let mut outnodes = OutnodeReports::new();
+            let mut scheduler = Scheduler::new();
+            scheduler.every(Job::LeaderTimeout, LEADER_TIMEOUT_PERIOD);
+            scheduler.every(Job::MempoolExpiry, MEMPOOL_EXPIRY_PERIOD);
+            scheduler.every(Job::BlockProduction, BLOCK_PRODUCTION_PERIOD);
+            let mut requests = Membership::requests();
+            let mut node = Node {
+                db,
+                swarm,
+                window,
+                peerid,
+                wallet,
+                private,
+                mempool: &mut mempool,
+                turn: &mut turn,
+                leader: &mut leader,
+                sync_state: &mut sync_state,
+                last_block: &mut last_block,
+                relay: &mut relay,
+                recieved_blocks: &mut recieved_blocks,
+                production: &production,
+                forks: &mut forks,
+                finality: &mut finality,
+                outnodes: &mut outnodes,
+            };
 
             // Main event handling loop
+            // scheduled jobs are run beside swarm events so polling of the swarm is never blocked
             'handler: loop {
-                match swarm.select_next_some().await {
+                let event = tokio::select! {
+                    event = node.swarm.select_next_some() => event,
+                    job = scheduler.next() => {
+                        match job {
+                            // Leave the handler so another relay will be dialed
+                            Job::RelayRedial => break 'handler,
+                            _ => run_job(job, &mut node).await,
+                        }
+                        continue 'handler;
+                    }
+                    // Exit, pause and resume requests of the operator
+                    Some(action) = requests.recv() => {
+                        if let Err(e) = Membership::announce(action, &mut node).await {
+                            window.emit("error", e).unwrap();
+                        }
+                        if action == Action::Exit {
+                            scheduler.after(Job::Shutdown, SHUTDOWN_DELAY);
+                        }
+                        continue 'handler;
+                    }
+                };
+                match event {
                     // Handle new connection establishment
                     SwarmEvent::ConnectionEstablished { peer_id, .. } => {
                         // Emit connection information to the UI
@@ -54,7 +126,7 @@ pub async fn handle(
                         window.emit("peerid", format!("{}", peerid)).unwrap();
 
                         // Update relay peer ID in the database
-                        match Relay::update(&mut relay, &db, Some(peer_id), None).await {
+                        match Relay::update(node.relay, db, Some(peer_id), None).await {
                             Ok(_) => {}
                             Err(e) => window.emit("error", e).unwrap(),
                         }
@@ -71,12 +143,12 @@ pub async fn handle(
                                 .unwrap(),
                             Err(e) => window.emit("error", e).unwrap(),
                         }
-                        break 'handler;
+                        scheduler.after(Job::RelayRedial, RELAY_REDIAL_DELAY);
                     }
 
                     // Handle connection closure
                     SwarmEvent::ConnectionClosed { .. } => {
-                        break 'handler;
+                        scheduler.after(Job::RelayRedial, RELAY_REDIAL_DELAY);
                     }
 
                     // Handle behavior events
@@ -85,8 +157,8 @@ pub async fn handle(
                         CentichainBehaviourEvent::Reqres(reqres) => match reqres {
                             Event::Message { message, .. } => match message {
                                 Message::Response { response, .. } => {
-                                    let cloned_relay = relay.clone();
-                                    match sync_state {
+                                    let cloned_relay = node.relay.clone();
+                                    match node.sync_state {
                                         Sync::NotSynced => {
                                             // Handle response for not synced state
                                             match Responses::handle(
@@ -95,59 +167,40 @@ pub async fn handle(
                                                 db,
                                                 wallet,
                                                 peerid,
-                                                &mut turn,
+                                                node.turn,
                                                 private,
-                                                &mut mempool,
-                                                &mut recieved_blocks,
-                                                &mut last_block,
-                                                &mut relay,
-                                                swarm,
-                                                &mut sync_state,
-                                                &mut leader,
+                                                node.mempool,
+                                                node.recieved_blocks,
+                                                node.last_block,
+                                                node.relay,
+                                                node.swarm,
+                                                node.sync_state,
+                                                node.leader,
                                             )
                                             .await
                                             {
                                                 Ok(_) => {
                                                     // Check turn after successful response handling
-                                                    match VSync::checking_turn(
-                                                        db,
-                                                        &mut mempool,
-                                                        &mut turn,
-                                                        &mut leader,
-                                                        wallet,
-                                                        peerid,
-                                                        private,
-                                                        &mut last_block,
-                                                        &mut relay,
-                                                        window,
-                                                        swarm,
-                                                        &mut sync_state,
-                                                    )
-                                                    .await
-                                                    {
+                                                    match VSync::checking_turn(&mut node).await {
                                                         Ok(_) => {}
                                                         Err(e) => {
                                                             // Disconnect on error
-                                                            swarm
+                                                            node.swarm
                                                                 .disconnect_peer_id(
-                                                                    relay.peerid.unwrap(),
+                                                                    node.relay.peerid.unwrap(),
                                                                 )
                                                                 .unwrap();
                                                             window.emit("error", e).unwrap()
                                                         }
                                                     }
 
-                                                    // Wait for a short period before updating UI
-                                                    thread::sleep(Duration::from_secs(7));
-                                                    // Update UI with current mempool and turn information
-                                                    window
-                                                        .emit("mempool", mempool.clone())
-                                                        .unwrap();
-                                                    window.emit("patience", turn.waiting).unwrap();
+                                                    // Update UI with current mempool and turn information after a short period
+                                                    scheduler
+                                                        .after(Job::UiRefresh, UI_REFRESH_DELAY);
                                                 }
                                                 Err(e) => {
                                                     // Disconnect on error
-                                                    swarm
+                                                    node.swarm
                                                         .disconnect_peer_id(
                                                             cloned_relay.peerid.unwrap(),
                                                         )
@@ -169,8 +222,15 @@ pub async fn handle(
                             libp2p::gossipsub::Event::Subscribed { peer_id, topic } => {
                                 // Add explicit peer for validator topic
                                 if topic.to_string() == "validator".to_string() {
-                                    swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
-                                    Handshake::start(relay.peerid.unwrap(), swarm, window);
+                                    node.swarm
+                                        .behaviour_mut()
+                                        .gossipsub
+                                        .add_explicit_peer(&peer_id);
+                                    Handshake::start(
+                                        node.relay.peerid.unwrap(),
+                                        node.swarm,
+                                        window,
+                                    );
                                 }
                             }
                             libp2p::gossipsub::Event::Message {
@@ -182,28 +242,14 @@ pub async fn handle(
                                 match GossipMessages::handle(
                                     message.data,
                                     propagation_source,
-                                    swarm,
-                                    window,
-                                    db,
-                                    &mut mempool,
-                                    &mut turn,
-                                    wallet,
-                                    peerid,
-                                    private,
-                                    &mut last_block,
-                                    &mut relay.clone(),
-                                    &mut leader,
-                                    &mut sync_state,
-                                    &mut recieved_blocks,
+                                    message.source,
+                                    &mut node,
                                 )
                                 .await
                                 {
                                     Ok(_) => {}
-                                    Err(e) => {
-                                        // Exit on error
-                                        window.emit("error", e).unwrap();
-                                        std::process::exit(0)
-                                    }
+                                    // Errors of a message are shown and the node keeps running
+                                    Err(e) => window.emit("error", e).unwrap(),
                                 }
                             }
                             _ => {}
@@ -216,3 +262,56 @@ pub async fn handle(
         Err(e) => window.emit("error", e).unwrap(),
     }
 }
+
+// Run a scheduled job of the event loop
+async fn run_job<S: Storage>(job: Job, node: &mut Node<'_, S>) {
+    let window = node.window;
+    match job {
+        Job::UiRefresh => {
+            window.emit("mempool", node.mempool.transactions()).unwrap();
+            window.emit("patience", node.turn.waiting).unwrap();
+        }
+
+        // Check deadline of the leader on its own, not only when transactions arrive
+        Job::LeaderTimeout => {
+            if let Sync::Synced = node.sync_state {
+                if let Err(e) = node
+                    .leader
+                    .check_timeout(
+                        node.db,
+                        node.swarm,
+                        node.peerid,
+                        node.wallet,
+                        node.private,
+                        window,
+                        node.turn,
+                        node.sync_state,
+                    )
+                    .await
+                {
+                    window.emit("error", e).unwrap();
+                }
+            }
+        }
+
+        Job::MempoolExpiry => {
+            if node.mempool.expire() > 0 {
+                window.emit("mempool", node.mempool.transactions()).unwrap();
+            }
+        }
+
+        // Seal a block in turn of the validator or wait for block of the leader by the production policy
+        Job::BlockProduction => {
+            let production = node.production;
+            if let Err(e) = production.tick(node).await {
+                window.emit("error", e).unwrap();
+            }
+        }
+
+        // Close the app after the exit message of the validator was sent
+        Job::Shutdown => std::process::exit(0),
+
+        // Handled by the event loop itself
+        Job::RelayRedial => {}
+    }
+}
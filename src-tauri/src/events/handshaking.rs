@@ -1,9 +1,4 @@
 use libp2p::{PeerId, Swarm};
-use mongodb::{
-    bson::{doc, from_document, Document},
-    options::FindOneOptions,
-    Collection, Database,
-};
 use serde::{Deserialize, Serialize};
 use sp_core::ed25519::Public;
 use tauri::Emitter;
@@ -12,11 +7,15 @@ use crate::{
     generator::{
         block::{block::Block, message::BlockMessage},
         leader::Leader,
+        mempool::Mempool,
         relay::Relay,
         swarm::{CentichainBehaviour, Req, Res},
         transaction::Transaction,
     },
-    tools::trun_sync::{Sync, Turn},
+    tools::{
+        storage::Storage,
+        trun_sync::{Sync, Turn},
+    },
 };
 
 use super::syncing::VSync;
@@ -56,15 +55,15 @@ impl Handshake {
 
     //get handshake resposne and deserialize it to handshake struct then check if the node is first node in the network generates Genesis Block
     //if its not first node start syncing
-    pub async fn response<'a>(
+    pub async fn response<'a, S: Storage>(
         window: &tauri::Window,
         response: Res,
-        db: &'a Database,
+        db: &'a S,
         wallet: &Public,
         peerid: &PeerId,
         private: &String,
         turn: &mut Turn,
-        mempool: &mut Vec<Transaction>,
+        mempool: &mut Mempool,
         recieved_blocks: &mut Vec<BlockMessage>,
         last_block: &mut Vec<Block>,
         relay: &mut Relay,
@@ -77,11 +76,30 @@ impl Handshake {
         match Relay::update(relay, db, None, Some(res.wallet)).await {
             Ok(_) => {
                 match res.first_node {
-                    //if validatore is first in the network,it must makes the Genesis Block and propagates it
-                    FirstChecker::Yes => {
-                        match BlockMessage::new(
+                    //if validatore is first in the network and has stored chain it continues from stored tip
+                    //validators of stored chain are stale because nobody else is in the network
+                    FirstChecker::Yes => match db.last_block().await {
+                        Ok(Some(tip)) => match db.clear_validators().await {
+                            Ok(_) => {
+                                window
+                                    .emit(
+                                        "status",
+                                        format!(
+                                            "Continuing stored chain from: {}",
+                                            tip.header.number
+                                        ),
+                                    )
+                                    .unwrap();
+                                last_block.clear();
+                                last_block.push(tip);
+                                Ok(())
+                            }
+                            Err(e) => Err(e),
+                        },
+                        //else it must makes the Genesis Block and propagates it
+                        Ok(None) => match BlockMessage::new(
                             db,
-                            Vec::new(),
+                            &mut Mempool::new(),
                             wallet,
                             peerid,
                             private,
@@ -94,31 +112,25 @@ impl Handshake {
                         )
                         .await
                         {
-                            Ok(block_message) => block_message.post(db, swarm, relay).await,
+                            Ok(block_message) => block_message.post(swarm, relay),
                             Err(e) => Err(e),
-                        }
-                    }
+                        },
+                        Err(e) => Err(e),
+                    },
 
                     //if validator was not first in the network then it hav to syncs with the network
                     FirstChecker::No => {
-                        //insert bsons that downloaded from relay then check recieved new blocks during the insert bson for syncing with the network
-                        match VSync::insert_bsons(window, db, mempool).await {
+                        //sync stored chain by the snapshot that downloaded from relay then check recieved new blocks during the syncing with the network
+                        match VSync::sync_chain(
+                            window, db, mempool, last_block, wallet, turn, sync_state,
+                        )
+                        .await
+                        {
                             Ok(_) => {
                                 let mut is_err = None;
-                                //finding last block after inserted bsons
-                                let collection: Collection<Document> = db.collection("Blocks");
-                                let options = FindOneOptions::builder()
-                                    .sort(doc! {"header.number": -1})
-                                    .build();
-                                let last_block_doc = collection
-                                    .find_one(doc! {})
-                                    .with_options(options)
-                                    .await
-                                    .unwrap()
-                                    .unwrap();
-                                //pushing last block
+                                //finding last block after inserted bsons and pushing it
                                 let deserialized_block_doc: Block =
-                                    from_document(last_block_doc).unwrap();
+                                    db.last_block().await.unwrap().unwrap();
                                 last_block.clear();
                                 last_block.push(deserialized_block_doc);
                                 //validating each block in recieved block during the inserting bsons
@@ -126,23 +138,37 @@ impl Handshake {
                                     match Block::validation(
                                         &recieved_blocks[i].block,
                                         last_block,
-                                        &db,
+                                        db,
                                         mempool,
-                                        wallet,
                                         window,
                                         turn,
                                         sync_state,
                                     )
                                     .await
                                     {
-                                        Ok(block) => {
+                                        Ok((block, changes)) => {
+                                            //next leader of the message must be the elected validator
+                                            if let Err(e) = recieved_blocks[i]
+                                                .check_next_leader(db, &changes)
+                                                .await
+                                            {
+                                                Block::restore(mempool, block.body.transactions);
+                                                is_err.get_or_insert(e);
+                                                break;
+                                            }
                                             leader.update(
                                                 Some(recieved_blocks[i].next_leader),
                                                 window,
                                             );
 
                                             //insert block to the database
-                                            match block.insertion(db).await {
+                                            match block
+                                                .insertion(
+                                                    db, &changes, mempool, last_block, wallet,
+                                                    window,
+                                                )
+                                                .await
+                                            {
                                                 Ok(_) => {}
                                                 Err(e) => {
                                                     is_err.get_or_insert(e);
@@ -151,7 +177,7 @@ impl Handshake {
                                             }
                                         }
                                         Err(e) => {
-                                            is_err.get_or_insert(e);
+                                            is_err.get_or_insert(e.message());
                                             break;
                                         }
                                     }
@@ -0,0 +1,42 @@
+use libp2p::{PeerId, Swarm};
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{
+            attestation::Finality, block::Block, forks::ForkPool, message::BlockMessage,
+            production::Production,
+        },
+        leader::Leader,
+        mempool::Mempool,
+        outnode::OutnodeReports,
+        relay::Relay,
+        swarm::CentichainBehaviour,
+    },
+    tools::{
+        storage::Storage,
+        trun_sync::{Sync, Turn},
+    },
+};
+
+// State of the node that the event loop threads into handlers of messages and scheduled jobs
+// fields are borrowed one by one, so a handler can pass some of them on and keep using the others
+pub struct Node<'n, S: Storage> {
+    pub db: &'n S,
+    pub swarm: &'n mut Swarm<CentichainBehaviour>,
+    pub window: &'n tauri::Window,
+    pub peerid: &'n PeerId,
+    pub wallet: &'n Public,
+    pub private: &'n String,
+    pub mempool: &'n mut Mempool,
+    pub turn: &'n mut Turn,
+    pub leader: &'n mut Leader,
+    pub sync_state: &'n mut Sync,
+    pub last_block: &'n mut Vec<Block>,
+    pub relay: &'n mut Relay,
+    pub recieved_blocks: &'n mut Vec<BlockMessage>, //blocks that are received during syncing
+    pub production: &'n Production,
+    pub forks: &'n mut ForkPool,
+    pub finality: &'n mut Finality,
+    pub outnodes: &'n mut OutnodeReports,
+}
@@ -1,20 +1,13 @@
-use mongodb::{
-    bson::{doc, from_document, Document},
-    Collection, Database,
-};
 use reqwest::Client;
 
-use crate::generator::relay::Relay;
+use crate::tools::storage::Storage;
 
 pub struct OutgoingConnection;
 
 impl OutgoingConnection {
-    pub async fn delete_post<'a>(db: &'a Database) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("relay");
-        let filter = doc! {};
-        let query = collection.find_one(filter).await;
-        if let Ok(Some(doc)) = query {
-            let relay: Relay = from_document(doc).unwrap();
+    pub async fn delete_post<'a, S: Storage>(db: &'a S) -> Result<(), &'a str> {
+        let query = db.relay().await;
+        if let Ok(Some(relay)) = query {
             let addr = relay.addr;
 
             let client = Client::new();
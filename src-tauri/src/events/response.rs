@@ -1,16 +1,18 @@
 use libp2p::{PeerId, Swarm};
-use mongodb::Database;
 use sp_core::ed25519::Public;
 
 use crate::{
     generator::{
         block::{block::Block, message::BlockMessage},
         leader::Leader,
+        mempool::Mempool,
         relay::Relay,
         swarm::{CentichainBehaviour, Res},
-        transaction::Transaction,
     },
-    tools::trun_sync::{Sync, Turn},
+    tools::{
+        storage::Storage,
+        trun_sync::{Sync, Turn},
+    },
 };
 
 use super::{handshaking::Handshake, syncing::VSync};
@@ -18,15 +20,15 @@ use super::{handshaking::Handshake, syncing::VSync};
 pub struct Responses;
 
 impl Responses {
-    pub async fn handle<'a>(
+    pub async fn handle<'a, S: Storage>(
         window: &tauri::Window,
         response: Res,
-        db: &'a Database,
+        db: &'a S,
         wallet: &'a Public,
         peerid: &'a PeerId,
         trun: &mut Turn,
         private: &'a String,
-        mempool: &mut Vec<Transaction>,
+        mempool: &mut Mempool,
         recieved_blocks: &mut Vec<BlockMessage>,
         last_block: &mut Vec<Block>,
         relay: &mut Relay,
@@ -39,7 +41,7 @@ impl Responses {
         match Handshake::response(
             window,
             response,
-            &db,
+            db,
             wallet,
             peerid,
             private,
@@ -55,14 +57,18 @@ impl Responses {
         .await
         {
             Ok(_) => {
-                let sync = VSync::new(
+                match VSync::new(
                     &relay.peerid.unwrap(),
                     peerid,
                     "I'm Synced".to_string(),
+                    last_block,
                     wallet,
-                );
-                match sync.propagate(swarm, window) {
-                    Ok(_) => Ok(sync_state.synced()),
+                    private,
+                ) {
+                    Ok(sync) => match sync.propagate(swarm, window) {
+                        Ok(_) => Ok(sync_state.synced()),
+                        Err(e) => Err(e),
+                    },
                     Err(e) => Err(e),
                 }
             }
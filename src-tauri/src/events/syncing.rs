@@ -1,80 +1,155 @@
-use std::{fs, path::Path};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
 
+use centichain_keypair::CentichainKey;
 use libp2p::{gossipsub::IdentTopic, PeerId, Swarm};
-use mongodb::{
-    bson::{doc, to_document, Document},
-    Collection, Database,
-};
+use mongodb::bson::{from_document, Document};
 use serde::{Deserialize, Serialize};
-use sp_core::ed25519::Public;
+use sp_core::{
+    ed25519::{Public, Signature},
+    Pair,
+};
 use tauri::Emitter;
 
 use crate::{
     generator::{
-        block::{block::Block, message::BlockMessage},
-        leader::Leader,
+        block::{block::Block, forks::MAX_REORG_DEPTH, header::Sign, message::BlockMessage},
+        encoding::Canonical,
+        mempool::Mempool,
         relay::Relay,
         swarm::CentichainBehaviour,
-        transaction::Transaction,
         validator::Validator,
     },
     tools::{
         bsons::Bson,
+        changeset::ChangeSet,
         downloader::Downloader,
+        invalid::Invalid,
+        settings::Settings,
+        storage::Storage,
         trun_sync::{Sync, Turn},
         zipp::Zip,
     },
 };
 
-use super::gossip_messages::handler::GossipMessages;
+use super::{gossip_messages::handler::GossipMessages, node::Node};
 
+// Registration message of a synced validator
+// it is signed by the wallet of the validator and binds its peer id to the chain tip that it synced to
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VSync {
     relay: PeerId,
     peerid: PeerId,
     msg: String,
-    wallet: Public,
+    number: u64,
+    tip: String,
+    signature: Sign,
 }
 
 impl VSync {
-    pub fn new(relay: &PeerId, peerid: &PeerId, msg: String, wallet: &Public) -> Self {
-        Self {
+    pub fn new<'a>(
+        relay: &PeerId,
+        peerid: &PeerId,
+        msg: String,
+        last_block: &Vec<Block>,
+        wallet: &Public,
+        private: &String,
+    ) -> Result<Self, &'a str> {
+        let (number, tip) = match last_block.first() {
+            Some(block) => (block.header.number, block.header.hash.clone()),
+            None => (0, String::new()),
+        };
+        let mut vsync = Self {
             relay: *relay,
             peerid: *peerid,
             msg,
-            wallet: *wallet,
+            number,
+            tip,
+            signature: Sign {
+                signatgure: Signature::from_raw([0; 64]),
+                key: *wallet,
+            },
+        };
+        match CentichainKey::signing(private, &vsync.hash()) {
+            Ok(signed) => {
+                vsync.signature.signatgure = signed;
+                Ok(vsync)
+            }
+            Err(_) => Err("Error during signing the sync message-(events/syncing 80)"),
+        }
+    }
+
+    fn hash(&self) -> String {
+        self.canonical_hash("vsync")
+    }
+
+    // Check the message before registering its validator
+    // author is the signed source of the gossip message, so nobody can register another peer id,
+    // and the tip must be a recent block of this node's chain, so old messages can't be replayed
+    async fn verify<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        author: Option<PeerId>,
+        last_block: &Vec<Block>,
+    ) -> Result<Option<&'a str>, &'a str> {
+        if author != Some(self.peerid) {
+            return Ok(Some("Sync message is not from its validator"));
+        }
+        if !sp_core::ed25519::Pair::verify(
+            &self.signature.signatgure,
+            self.hash(),
+            &self.signature.key,
+        ) {
+            return Ok(Some("Sync message has an incorrect signature"));
+        }
+        match last_block.first() {
+            Some(last) if self.number.saturating_add(MAX_REORG_DEPTH) > last.header.number => {}
+            _ => return Ok(Some("Sync message is not on a recent block")),
+        }
+        match db.find_block(self.number).await {
+            Ok(Some(block)) if block.header.hash == self.tip => Ok(None),
+            Ok(_) => Ok(Some("Sync message is not on the chain of this node")),
+            Err(e) => Err(e),
         }
     }
 
     //handle gossip messages that are VSync model
-    pub async fn handle<'a>(
+    pub async fn handle<'a, S: Storage>(
         &self,
-        db: &'a Database,
+        db: &'a S,
+        author: Option<PeerId>,
+        last_block: &Vec<Block>,
         window: &tauri::Window,
     ) -> Result<(), &'a str> {
-        match Validator::new(db, self.peerid, self.relay, self.wallet).await {
+        match self.verify(db, author, last_block).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => {
+                return Ok(window
+                    .emit("status", format!("{}: {}", reason, self.peerid))
+                    .unwrap())
+            }
+            Err(e) => return Err(e),
+        }
+
+        match Validator::new(db, self.peerid, self.relay, self.signature.key).await {
             Ok(validator) => {
-                let collection: Collection<Document> = db.collection("validators");
-                let filter = to_document(&validator).unwrap();
-                let query = collection.find_one(filter).await;
+                let query = db.find_validator(&validator.peerid).await;
 
                 //check validators and if it doesn't include new node peer id then insert new node as validator
-                if let Ok(Some(_doc)) = query {
+                if let Ok(Some(_validator)) = query {
                     Ok(())
                 } else {
-                    match collection
-                        .insert_one(to_document(&validator).unwrap())
-                        .await
-                    {
+                    match db.insert_validator(&validator).await {
                         Ok(_) => Ok(window
                             .emit(
                                 "status",
                                 &format!("New synced validator added: {}", self.peerid),
                             )
                             .unwrap()),
-                        Err(_) => {
-                            Err("Error while inserting new validator-(relay/tools/syncer 63)")
-                        }
+                        Err(e) => Err(e),
                     }
                 }
             }
@@ -104,7 +179,10 @@ impl VSync {
     }
 
     //download and extract the blockchain and then insert it to database
-    async fn get_blockchain<'a>(window: &tauri::Window, db: &'a Database) -> Result<(), &'a str> {
+    async fn get_blockchain<'a, S: Storage>(
+        window: &tauri::Window,
+        db: &'a S,
+    ) -> Result<(), &'a str> {
         //get connected relay ip and make blockchain download link from it
         let relay_ip = Relay::ip_adress(db).await;
         match relay_ip {
@@ -123,28 +201,199 @@ impl VSync {
         }
     }
 
+    //sync stored chain with the network by the snapshot of connected relay
+    //blocks of the snapshot after the stored tip are validated and inserted, the full snapshot is inserted
+    //only if stored chain is empty, corrupt, too far behind or doesn't match the snapshot
+    pub async fn sync_chain<'a, S: Storage>(
+        window: &tauri::Window,
+        db: &'a S,
+        mempool: &mut Mempool,
+        last_block: &mut Vec<Block>,
+        wallet: &Public,
+        turn: &mut Turn,
+        sync_state: &mut Sync,
+    ) -> Result<(), &'a str> {
+        match Self::get_blockchain(window, db).await {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+        match db.last_block().await {
+            Ok(Some(tip)) => {
+                window
+                    .emit("status", format!("Stored chain tip: {}", tip.header.number))
+                    .unwrap();
+                last_block.clear();
+                last_block.push(tip);
+                match Self::catch_up(window, db, mempool, last_block, wallet, turn, sync_state)
+                    .await
+                {
+                    Ok(true) => Ok(()),
+                    Ok(false) => {
+                        window
+                            .emit("status", "Stored chain can not be resumed, full syncing...")
+                            .unwrap();
+                        Self::insert_bsons(window, db, mempool).await
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Ok(None) => Self::insert_bsons(window, db, mempool).await,
+            Err(_) => {
+                window
+                    .emit("status", "Stored chain is corrupt, full syncing...")
+                    .unwrap();
+                Self::insert_bsons(window, db, mempool).await
+            }
+        }
+    }
+
+    //validate and insert blocks of the snapshot that are after the stored tip (that is in last block)
+    //it returns false if stored chain doesn't match the snapshot or is too far behind it
+    async fn catch_up<'a, S: Storage>(
+        window: &tauri::Window,
+        db: &'a S,
+        mempool: &mut Mempool,
+        last_block: &mut Vec<Block>,
+        wallet: &Public,
+        turn: &mut Turn,
+        sync_state: &mut Sync,
+    ) -> Result<bool, &'a str> {
+        let max_catchup_blocks = Settings::load().max_catchup_blocks;
+        let path = "./etc/dump/Centichain";
+        let tip = match last_block.first() {
+            Some(tip) => tip.clone(),
+            None => return Ok(false),
+        };
+
+        //find missing blocks in the snapshot and check that the stored tip is in it
+        let mut missing: Vec<Block> = Vec::new();
+        let mut tip_found = false;
+        let mut snapshot_tip: Option<Block> = None;
+        match File::open(format!("{}/Blocks.bson", path)) {
+            Ok(file) => {
+                let mut reader = BufReader::new(file);
+                while let Ok(doc) = Document::from_reader(&mut reader) {
+                    let block: Block = match from_document(doc) {
+                        Ok(block) => block,
+                        Err(_) => {
+                            return Err("Block of the snapshot is corrupt-(events/syncing 278)")
+                        }
+                    };
+                    if snapshot_tip
+                        .as_ref()
+                        .map_or(true, |s| s.header.number < block.header.number)
+                    {
+                        snapshot_tip = Some(block.clone());
+                    }
+                    if block.header.number == tip.header.number {
+                        if block.header.hash != tip.header.hash {
+                            return Ok(false);
+                        }
+                        tip_found = true;
+                    } else if block.header.number > tip.header.number {
+                        if missing.len() as u64 >= max_catchup_blocks {
+                            return Ok(false);
+                        }
+                        missing.push(block);
+                    }
+                }
+            }
+            Err(_) => return Err("Blocks of snapshot not found-(events/syncing 300)"),
+        }
+        //if snapshot is behind stored tip then its tip must be in the stored chain
+        if !tip_found {
+            match snapshot_tip {
+                Some(snapshot_tip) => match db.find_block(snapshot_tip.header.number).await {
+                    Ok(Some(block)) if block.header.hash == snapshot_tip.header.hash => {}
+                    Ok(_) => return Ok(false),
+                    Err(e) => return Err(e),
+                },
+                None => return Ok(false),
+            }
+        }
+
+        //validate and insert missing blocks in order
+        missing.sort_by_key(|block| block.header.number);
+        for block in missing {
+            match block
+                .validation(last_block, db, mempool, window, turn, sync_state)
+                .await
+            {
+                Ok((block, changes)) => match block
+                    .insertion(db, &changes, mempool, last_block, wallet, window)
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(e) => return Err(e),
+                },
+                Err(Invalid::Storage(e)) => return Err(e),
+                Err(_) => return Ok(false),
+            }
+        }
+
+        //validators of the snapshot are not signed so the stored registry is kept
+        //waiting of the validators was updated by the inserted blocks and new validators are added by their sync messages
+
+        //transactions of the snapshot spend stored utxos so they will be validated before adding to mempool
+        if Path::new(&format!("{}/transactions.bson", path)).exists() {
+            let mut transactions = Mempool::new();
+            match Bson::add_mempool(&mut transactions, "transactions.bson", window).await {
+                Ok(_) => {
+                    for transaction in transactions.transactions() {
+                        if transaction
+                            .validate(db, &mut ChangeSet::new())
+                            .await
+                            .is_ok()
+                        {
+                            let _ = mempool.insert(transaction);
+                        }
+                    }
+                    window.emit("mempool", mempool.transactions()).unwrap();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        window.emit("status", "Missing blocks synced").unwrap();
+        Ok(true)
+    }
+
     //after getting blockchain and unzip it to bson files syncing start inserting thos into database
-    pub async fn insert_bsons<'a>(
+    pub async fn insert_bsons<'a, S: Storage>(
         window: &tauri::Window,
-        db: &'a Database,
-        mempool: &mut Vec<Transaction>,
+        db: &'a S,
+        mempool: &mut Mempool,
     ) -> Result<(), &'a str> {
         let mut error = None;
 
-        // Get blockchain and unzip it
-        match Self::get_blockchain(window, db).await {
+        // Remove stored chain state and insert the full snapshot
+        match db.clear().await {
             Ok(_) => {
                 //add bsons to database or mempool by a loop
                 let path = "./etc/dump/Centichain";
-                for entry in fs::read_dir(path).unwrap() {
-                    let item = entry.unwrap();
-                    let file_name = item.file_name();
-                    let collection_name =
-                        Path::new(&file_name).file_stem().unwrap().to_str().unwrap();
-                    let str_name = file_name.to_str().unwrap();
+                let entries = match fs::read_dir(path) {
+                    Ok(entries) => entries,
+                    Err(_) => return Err("Snapshot directory not found-(events/syncing 374)"),
+                };
+                for entry in entries {
+                    let file_name = match entry {
+                        Ok(item) => item.file_name(),
+                        Err(_) => {
+                            error = Some("Snapshot directory is unreadable-(events/syncing 380)");
+                            break;
+                        }
+                    };
+                    let (collection_name, str_name) =
+                        match (Path::new(&file_name).file_stem(), file_name.to_str()) {
+                            (Some(stem), Some(name)) => match stem.to_str() {
+                                Some(stem) => (stem, name),
+                                None => continue,
+                            },
+                            _ => continue,
+                        };
                     if str_name.contains("bson") {
                         if str_name == "transactions.bson" {
-                            match Bson::add(None, None, Some(mempool), &str_name, window).await {
+                            match Bson::add_mempool(mempool, str_name, window).await {
                                 Ok(_) => {}
                                 Err(e) => {
                                     error = Some(e);
@@ -152,15 +401,8 @@ impl VSync {
                                 }
                             }
                         } else {
-                            match Bson::add(
-                                Some(db),
-                                Some(&collection_name),
-                                None,
-                                &str_name,
-                                window,
-                            )
-                            .await
-                            {
+                            let bson_addr = format!("{}/{}", path, str_name);
+                            match Bson::add(db, collection_name, &bson_addr, window).await {
                                 Ok(_) => {}
                                 Err(e) => {
                                     error = Some(e);
@@ -172,85 +414,85 @@ impl VSync {
                 }
 
                 //if error was some return it and if not continues syncing
-                if error.is_some() {
-                    Err(error.unwrap())
-                } else {
-                    Ok(window
-                        .emit("status", "Blockchain inserted successfully")
-                        .unwrap())
+                //utxos of the snapshot may be waiting in the cache for a block, so they are written now
+                match error {
+                    Some(e) => Err(e),
+                    None => match db.flush().await {
+                        Ok(_) => Ok(window
+                            .emit("status", "Blockchain inserted successfully")
+                            .unwrap()),
+                        Err(e) => Err(e),
+                    },
                 }
             }
             Err(e) => Err(e),
         }
     }
 
-    pub async fn checking_turn<'a>(
-        db: &'a Database,
-        mempool: &mut Vec<Transaction>,
-        turn: &mut Turn,
-        leader: &mut Leader,
-        wallet: &Public,
-        peerid: &PeerId,
-        private: &String,
-        last_block: &mut Vec<Block>,
-        relay: &mut Relay,
-        window: &tauri::Window,
-        swarm: &mut Swarm<CentichainBehaviour>,
-        sync_state: &mut Sync,
-    ) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        let validators_count = collection.count_documents(doc! {}).await;
+    pub async fn checking_turn<'n, S: Storage>(node: &mut Node<'n, S>) -> Result<(), &'n str> {
+        let validators_count = node.db.validators_count().await;
         match validators_count {
             Ok(count) => {
                 if count == 0 {
-                    turn.on(window);
-                    leader.update(None, window);
-                    window.emit("turn", turn.shift.to_string()).unwrap();
-
-                    if mempool.len() > 1 {
-                        //push 51 transaction to block's transactions from mempool
-                        let mut transactions = Vec::new();
-                        while mempool.len() > 0 {
-                            if transactions.len() < 51 {
-                                transactions.push(mempool.remove(0));
-                            } else {
-                                break;
-                            }
-                        }
+                    node.turn.on(node.window);
+                    node.leader.update(None, node.window);
+                    node.window
+                        .emit("turn", node.turn.shift.to_string())
+                        .unwrap();
 
+                    if node.production.should_seal(node.mempool, node.last_block) {
                         //make new block mssage (include new block and next leader)
+                        //block takes transactions of mempool with the highest fee rates
                         match BlockMessage::new(
-                            db,
-                            transactions,
-                            wallet,
-                            peerid,
-                            private,
-                            last_block,
-                            relay,
-                            turn,
-                            leader,
-                            window,
-                            sync_state,
+                            node.db,
+                            node.mempool,
+                            node.wallet,
+                            node.peerid,
+                            node.private,
+                            node.last_block,
+                            node.relay,
+                            node.turn,
+                            node.leader,
+                            node.window,
+                            node.sync_state,
                         )
                         .await
                         {
                             Ok(block_message) => {
-                                window.emit("mempool", mempool.clone()).unwrap(); //show mempool in front
-                                block_message.post(db, swarm, relay).await
+                                //show mempool in front
+                                node.window
+                                    .emit("mempool", node.mempool.transactions())
+                                    .unwrap();
+                                block_message.post(node.swarm, node.relay)
                             }
                             Err(e) => Err(e),
                         }
                     } else {
-                        window.emit("turn", turn.shift.to_string()).unwrap();
+                        node.window
+                            .emit("turn", node.turn.shift.to_string())
+                            .unwrap();
                         Ok(())
                     }
                 } else {
-                    turn.off(count as u16, window);
-                    window.emit("turn", turn.shift.to_string()).unwrap();
+                    node.turn.off(count as u16, node.window);
+                    node.window
+                        .emit("turn", node.turn.shift.to_string())
+                        .unwrap();
                     Ok(())
                 }
             }
-            Err(_) => Err("Problem during get count of validators-(events/syncing 188)"),
+            Err(_) => Err("Problem during get count of validators-(events/syncing 482)"),
         }
     }
 }
+
+// Fields of the sync message that its signature commits to
+impl Canonical for VSync {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.relay.encode(out);
+        self.peerid.encode(out);
+        self.msg.encode(out);
+        self.number.encode(out);
+        self.tip.encode(out);
+    }
+}
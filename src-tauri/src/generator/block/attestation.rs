@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use centichain_keypair::CentichainKey;
+use libp2p::{gossipsub::IdentTopic, PeerId};
+use serde::{Deserialize, Serialize};
+use sp_core::{
+    ed25519::{Public, Signature},
+    Pair,
+};
+use tauri::Emitter;
+
+use crate::{
+    events::{gossip_messages::handler::GossipMessages, node::Node},
+    generator::{encoding::Canonical, validator::Validator},
+    tools::storage::Storage,
+};
+
+//attestations of blocks higher than the stored tip plus this are not kept
+const ATTESTATION_WINDOW: u64 = 5;
+
+use super::{block::Block, header::Sign};
+
+// Signed statement of a validator that it validated and inserted the block
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attestation {
+    pub validator: PeerId,
+    pub number: u64,
+    pub hash: String,
+    pub signature: Sign,
+}
+
+// Finality certificate of a block that more than two thirds of validators attested
+// the block generator is counted by its block signature (signature of the block hash), so it doesn't attest its own block
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Certificate {
+    pub number: u64,
+    pub hash: String,
+    pub generator: PeerId,
+    pub signature: Sign,
+    pub attestations: Vec<Attestation>,
+}
+
+impl Attestation {
+    pub fn new<'a>(
+        block: &Block,
+        peerid: &PeerId,
+        wallet: &Public,
+        private: &String,
+    ) -> Result<Self, &'a str> {
+        let mut attestation = Self {
+            validator: *peerid,
+            number: block.header.number,
+            hash: block.header.hash.clone(),
+            signature: Sign {
+                signatgure: Signature::from_raw([0; 64]),
+                key: *wallet,
+            },
+        };
+        match CentichainKey::signing(private, &attestation.signed_hash()) {
+            Ok(signed) => {
+                attestation.signature.signatgure = signed;
+                Ok(attestation)
+            }
+            Err(_) => Err("Error during signing the attestation-(generator/block/attestation 64)"),
+        }
+    }
+
+    fn signed_hash(&self) -> String {
+        self.canonical_hash("attestation")
+    }
+
+    // Check signature of the attestation (the wallet must be wallet of the validator in validators)
+    pub fn verify(&self) -> bool {
+        sp_core::ed25519::Pair::verify(
+            &self.signature.signatgure,
+            self.signed_hash(),
+            &self.signature.key,
+        )
+    }
+}
+
+// Fields of the attestation that its signature commits to
+impl Canonical for Attestation {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.validator.encode(out);
+        self.number.encode(out);
+        self.hash.encode(out);
+    }
+}
+
+impl Certificate {
+    // Check that more than two thirds of the validators signed the block (by attestations or as its generator)
+    // so anyone that has the validators can verify finality of the block without the block itself
+    pub fn verify(&self, validators: &[Validator]) -> bool {
+        let registered = |peerid: &PeerId, key: &Public| {
+            validators
+                .iter()
+                .any(|v| &v.peerid == peerid && &v.wallet == key)
+        };
+        let mut signers: HashSet<PeerId> = self
+            .attestations
+            .iter()
+            .filter(|a| a.number == self.number && a.hash == self.hash)
+            .filter(|a| registered(&a.validator, &a.signature.key) && a.verify())
+            .map(|a| a.validator)
+            .collect();
+        if registered(&self.generator, &self.signature.key)
+            && sp_core::ed25519::Pair::verify(
+                &self.signature.signatgure,
+                &self.hash,
+                &self.signature.key,
+            )
+        {
+            signers.insert(self.generator);
+        }
+        !validators.is_empty() && signers.len() * 3 > validators.len() * 2
+    }
+}
+
+// Attestations of blocks that are not final yet
+#[derive(Debug)]
+pub struct Finality {
+    attestations: Vec<Attestation>,
+}
+
+impl Finality {
+    pub fn new() -> Self {
+        Self {
+            attestations: Vec::new(),
+        }
+    }
+
+    // Attest an inserted block and gossip the attestation
+    pub async fn attest<'n, S: Storage>(
+        block: &Block,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        match Attestation::new(block, node.peerid, node.wallet, node.private) {
+            Ok(attestation) => {
+                let message = GossipMessages::Attestation(attestation.clone());
+                let str_message = serde_json::to_string(&message).unwrap();
+                match node
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(IdentTopic::new("validator"), str_message)
+                {
+                    Ok(_) => {
+                        node.finality
+                            .handle(attestation, node.db, node.window)
+                            .await
+                    }
+                    Err(_) => {
+                        Err("Error while gossiping attestation-(generator/block/attestation 154)")
+                    }
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Keep a correct attestation of a registered validator and check finality of its block
+    // only attestations between the final block and a little above the stored tip are kept, one per validator and height
+    pub async fn handle<'a, S: Storage>(
+        &mut self,
+        attestation: Attestation,
+        db: &'a S,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
+        match Self::finalized(db).await {
+            Ok(finalized) if attestation.number <= finalized => return Ok(()),
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+        match db.last_block().await {
+            Ok(Some(tip))
+                if attestation.number <= tip.header.number.saturating_add(ATTESTATION_WINDOW) => {}
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        if self
+            .attestations
+            .iter()
+            .any(|a| a.validator == attestation.validator && a.number == attestation.number)
+        {
+            return Ok(());
+        }
+
+        match db.find_validator(&attestation.validator).await {
+            Ok(Some(validator)) => {
+                if validator.wallet != attestation.signature.key || !attestation.verify() {
+                    return Ok(window
+                        .emit(
+                            "status",
+                            format!("Incorrect attestation from: {}", attestation.validator),
+                        )
+                        .unwrap());
+                }
+            }
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        self.attestations.push(attestation);
+        self.check(db, window).await
+    }
+
+    // Make the certificate of the highest stored block that has more than two thirds of validators' attestations
+    async fn check<'a, S: Storage>(
+        &mut self,
+        db: &'a S,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
+        let validators = match db.validators().await {
+            Ok(validators) => validators,
+            Err(e) => return Err(e),
+        };
+
+        let mut numbers: Vec<u64> = self.attestations.iter().map(|a| a.number).collect();
+        numbers.sort();
+        numbers.dedup();
+        for number in numbers.into_iter().rev() {
+            let block = match db.find_block(number).await {
+                Ok(Some(block)) => block,
+                Ok(None) => continue,
+                Err(e) => return Err(e),
+            };
+
+            //only attestations of the stored block are counted (attestations of competing blocks wait)
+            let attestations: Vec<Attestation> = self
+                .attestations
+                .iter()
+                .filter(|a| a.number == number && a.hash == block.header.hash)
+                .cloned()
+                .collect();
+            let certificate = Certificate {
+                number,
+                hash: block.header.hash,
+                generator: block.header.validator,
+                signature: block.header.signature,
+                attestations,
+            };
+
+            if certificate.verify(&validators) {
+                return match db.set_finality(&certificate).await {
+                    Ok(_) => {
+                        self.attestations.retain(|a| a.number > number);
+                        Ok(window.emit("finalized", number).unwrap())
+                    }
+                    Err(e) => Err(e),
+                };
+            }
+        }
+        Ok(())
+    }
+
+    // Height of the last final block (0 if there is no final block)
+    pub async fn finalized<'a, S: Storage>(db: &'a S) -> Result<u64, &'a str> {
+        match db.finality().await {
+            Ok(certificate) => Ok(certificate.map(|c| c.number).unwrap_or(0)),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -1,21 +1,28 @@
 use libp2p::PeerId;
-use mongodb::{
-    bson::{to_document, Document},
-    Collection, Database,
-};
 use serde::{Deserialize, Serialize};
-use sp_core::{ed25519::Public, Pair};
+use sp_core::ed25519::Public;
+use tauri::Emitter;
 
 use crate::{
-    generator::{relay::Relay, transaction::Transaction, HashMaker},
+    events::gossip_messages::transactions::Transactions,
+    generator::{
+        encoding::Canonical,
+        mempool::Mempool,
+        merkle::{MerkleTree, Proof},
+        relay::Relay,
+        transaction::{Conflict, Transaction},
+    },
     tools::{
+        changeset::ChangeSet,
+        invalid::Invalid,
+        storage::Storage,
         trun_sync::{Sync, Turn},
         utxo::UTXO,
         waiting::Waiting,
     },
 };
 
-use super::{coinbase::Coinbase, header::Header};
+use super::{coinbase::Coinbase, header::Header, production::Production};
 
 // Define the structure of a block, including its header and body.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -32,7 +39,21 @@ pub struct Body {
     pub transactions: Vec<Transaction>,
 }
 
+impl Canonical for Body {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.coinbase.encode(out);
+        self.transactions.encode(out);
+    }
+}
+
 impl Body {
+    //leaves of the merkle tree of the block (coinbase first and then transactions in their order)
+    pub fn leaves(&self) -> Vec<&String> {
+        let mut leaves = vec![&self.coinbase.hash];
+        leaves.extend(self.transactions.iter().map(|trx| &trx.hash));
+        leaves
+    }
+
     fn new(coinbase: Coinbase, transactions: Vec<Transaction>) -> Self {
         Self {
             coinbase,
@@ -41,12 +62,15 @@ impl Body {
     }
 }
 
+//count of transactions that a block can have (production policy can make blocks smaller)
+pub const MAX_TRANSACTIONS: usize = 51;
+
 impl Block {
-    //make a new block
-    //if transactions len was 0 then block generate a genesis block*********************************************************
-    pub async fn new<'a>(
-        db: &'a Database,
-        transactions: Vec<Transaction>,
+    //make a new block from the transactions of mempool with the highest fee rates
+    //if mempool was empty then block generate a genesis block*********************************************************
+    pub async fn new<'a, S: Storage>(
+        db: &'a S,
+        mempool: &mut Mempool,
         wallet: &Public,
         peerid: &PeerId,
         private: &String,
@@ -56,6 +80,15 @@ impl Block {
         turn: &mut Turn,
         sync_state: &mut Sync,
     ) -> Result<Self, &'a str> {
+        //stage spending of transactions and skip the ones that are not valid anymore
+        let mut changes = ChangeSet::new();
+        let mut transactions = Vec::new();
+        for trx in mempool.template(Production::new().max_transactions) {
+            if trx.validate(db, &mut changes).await.is_ok() {
+                transactions.push(trx);
+            }
+        }
+
         //make coinbase transaction and if there is no ny problem make header and then return new block
         let coinbase = Coinbase::new(&transactions, wallet, last_block, relay).await;
 
@@ -63,7 +96,8 @@ impl Block {
         let body = Body::new(coinbase, transactions);
 
         //make header of block
-        match Header::new(
+        //transactions are taken out of mempool, so they are put back if the block is not made
+        let header = match Header::new(
             wallet,
             &body,
             peerid,
@@ -73,76 +107,93 @@ impl Block {
         )
         .await
         {
-            Ok(header) => {
-                let block = Self { header, body };
+            Ok(header) => header,
+            Err(e) => {
+                Self::restore(mempool, body.transactions);
+                return Err(e);
+            }
+        };
+        let block = Self { header, body };
 
-                //inseting new outputx as utxo into databse
-                match Self::handle_outputs(&block, db, wallet, window).await {
-                    //update waiting of validators
-                    Ok(_) => match Waiting::update(db, peerid, turn, window, sync_state).await {
-                        Ok(_) => {
-                            //change last block
-                            last_block.clear();
-                            last_block.push(block.clone());
+        //staging new outputs as utxos and waiting of validators
+        block.handle_outputs(&mut changes);
+        if let Err(e) = Waiting::update(db, peerid, turn, window, sync_state, &mut changes).await {
+            Self::restore(mempool, block.body.transactions);
+            return Err(e);
+        }
 
-                            Ok(block)
-                        }
-                        Err(e) => Err(e),
-                    },
-                    Err(e) => Err(e),
+        //own block is committed as soon as it is made
+        match block
+            .clone()
+            .insertion(db, &changes, mempool, last_block, wallet, window)
+            .await
+        {
+            Ok(_) => Ok(block),
+            Err(e) => {
+                //the block may be committed and only updating the front failed
+                if last_block.first().map(|b| &b.header.hash) != Some(&block.header.hash) {
+                    Self::restore(mempool, block.body.transactions);
                 }
+                Err(e)
             }
-            //return error if header had problem
-            Err(e) => Err(e),
         }
     }
 
     //Block Validation*******************************************************************************
-    pub async fn validation<'a>(
+    //validation doesn't write into database, it returns the block with its staged changes for insertion
+    pub async fn validation<'a, S: Storage>(
         &self,
         last_block: &mut Vec<Self>,
-        db: &'a Database,
-        mempool: &mut Vec<Transaction>,
-        wallet: &Public,
+        db: &'a S,
+        mempool: &mut Mempool,
         window: &tauri::Window,
         turn: &mut Turn,
         sync_state: &mut Sync,
-    ) -> Result<Self, &'a str> {
+    ) -> Result<(Self, ChangeSet), Invalid<'a>> {
+        if last_block[0].header.number.checked_add(1) != Some(self.header.number) {
+            return Err(Invalid::Rejected(
+                "Block rejected, its number doesn't follow the last block.",
+            ));
+        }
         if last_block[0].header.hash == self.header.previous {
-            //check block signature that its signature data is block hash(block hash is hash of body)
-            let str_of_body = serde_json::to_string(&self.body).unwrap();
-            let hash_of_body = HashMaker::generate(&str_of_body);
-            let sign_check = sp_core::ed25519::Pair::verify(
-                &self.header.signature.signatgure,
-                hash_of_body,
-                &self.header.signature.key,
-            );
+            let sign_check = self.check_signature();
 
             //if block signature was correct then validation start validating of transactions in body of block
             //if found even 1 incorrect trx then block will be rejected
             if sign_check {
+                if self.body.transactions.len() > MAX_TRANSACTIONS {
+                    return Err(Invalid::Rejected(
+                        "Block rejected, it has more transactions than a block can have.",
+                    ));
+                }
+
+                //a utxo can't be spent twice in the block
+                if let Some(conflict) = Conflict::in_block(&self.body.transactions) {
+                    window.emit("conflict", conflict).unwrap();
+                    return Err(Invalid::Rejected(
+                        "Block rejected, transactions of block spend a utxo twice.",
+                    ));
+                }
+
                 //validate transactions in body
+                let mut changes = ChangeSet::new();
                 let mut trx_err = None;
                 let mut trx_backup: Vec<Transaction> = Vec::new();
-                for i in 0..self.body.transactions.len() {
-                    let index = mempool
-                        .iter()
-                        .position(|trx| trx.hash == self.body.transactions[i].hash);
-                    //if trx was in mempool it means trx validated ans is correct so it removes from mempool
-                    //if trx was not in mempool it validates the trx and if it doesn't have any problems will be accepted
-                    if let Some(i) = index {
-                        trx_backup.push(mempool.remove(i));
-                    } else {
-                        match Transaction::validate(&self.body.transactions[i], db).await {
-                            Ok(_correcr) => {}
-                            Err(e) => {
-                                trx_err.get_or_insert(e);
-                                break;
-                            }
+                for trx in &self.body.transactions {
+                    //if trx was in mempool it removes from mempool
+                    //every trx is validated because mempool doesn't spend its inputs in database
+                    if let Some(trx) = mempool.remove(&trx.hash) {
+                        trx_backup.push(trx);
+                    }
+                    match Transaction::validate(trx, db, &mut changes).await {
+                        Ok(_correcr) => {}
+                        Err(e) => {
+                            trx_err.get_or_insert(e);
+                            break;
                         }
                     }
                 }
-                //if transactions of body doesn't have any problems then it goes to check coinbase transactions and insert utxos
+                //if transactions of body doesn't have any problems then it goes to check coinbase transactions and stage utxos
                 if trx_err.is_none() {
                     //validating coinbase of block and if it was correct then it will handle transactions of block
                     match Coinbase::validation(
@@ -153,138 +204,125 @@ impl Block {
                     .await
                     {
                         Ok(_) => {
-                            //if generating utxo in database doesn't have any errors then set waiting of validators
-                            //else return error of block
-                            match Self::handle_outputs(&self, db, wallet, window).await {
-                                Ok(_) => {
-                                    //if updating waiting doesn't any problems return block as correct block
-                                    //else return error of updating
-                                    match Waiting::update(
-                                        db,
-                                        &self.header.validator,
-                                        turn,
-                                        window,
-                                        sync_state,
-                                    )
-                                    .await
-                                    {
-                                        Ok(_) => {
-                                            //change last block
-                                            last_block.clear();
-                                            last_block.push(self.clone());
-
-                                            Ok(self.clone())
-                                        }
-                                        Err(e) => Err(e),
-                                    }
-                                }
+                            //stage new utxos and then waiting of validators
+                            self.handle_outputs(&mut changes);
+                            match Waiting::update(
+                                db,
+                                &self.header.validator,
+                                turn,
+                                window,
+                                sync_state,
+                                &mut changes,
+                            )
+                            .await
+                            {
+                                Ok(_) => Ok((self.clone(), changes)),
                                 Err(e) => {
-                                    //if there is any true trx in the rejected block that it removes its input utxos from database then it returns the trx to mempool
-                                    //before return an error about block rejection
-                                    if trx_backup.len() > 0 {
-                                        for t in trx_backup {
-                                            mempool.push(t);
-                                        }
-                                    }
-                                    Err(e)
+                                    //nothing is written for the rejected block so only its trxs return to mempool
+                                    Self::restore(mempool, trx_backup);
+                                    Err(Invalid::Storage(e))
                                 }
                             }
                         }
                         Err(e) => {
-                            //if there is any true trx in the rejected block that it removes its input utxos from database then it returns the trx to mempool
-                            //before return an error about block rejection
-                            if trx_backup.len() > 0 {
-                                for t in trx_backup {
-                                    mempool.push(t);
-                                }
-                            }
-                            Err(e)
+                            //nothing is written for the rejected block so only its trxs return to mempool
+                            Self::restore(mempool, trx_backup);
+                            Err(Invalid::Rejected(e))
                         }
                     }
                 } else {
-                    //if there is any true trx in the rejected block that it removes its input utxos from database then it returns the trx to mempool
-                    //before return an error about block rejection
-                    if trx_backup.len() > 0 {
-                        for t in trx_backup {
-                            mempool.push(t);
-                        }
-                    }
+                    //nothing is written for the rejected block so only its trxs return to mempool
+                    Self::restore(mempool, trx_backup);
                     Err(trx_err.unwrap())
                 }
             } else {
-                Err("Block signature is wrong and Block rejected.")
+                Err(Invalid::Rejected(
+                    "Block signature is wrong and Block rejected.",
+                ))
             }
         } else {
-            Err("Block validation problem!, previous hash doesn't match and Block rejected.")
+            Err(Invalid::Rejected(
+                "Block validation problem!, previous hash doesn't match and Block rejected.",
+            ))
         }
     }
 
-    //make and insert new utxos from outputs of coinbase and tranasactions into database*****************************************************
-    async fn handle_outputs<'a>(
-        &self,
-        db: &'a Database,
-        wallet: &Public,
-        window: &tauri::Window,
-    ) -> Result<(), &'a str> {
-        let mut utxo_err: Option<&str> = None;
+    //check block hash and signature by the version of its header
+    pub fn check_signature(&self) -> bool {
+        self.header.verify(&self.body)
+    }
+
+    //inclusion proof of the transaction (or coinbase) of the hash in merkle root of the header
+    pub fn proof(&self, hash: &String) -> Option<Proof> {
+        let leaves = self.body.leaves();
+        match leaves.iter().position(|leaf| *leaf == hash) {
+            Some(index) => MerkleTree::new(leaves).proof(hash, index),
+            None => None,
+        }
+    }
+
+    //return trxs of a rejected block to mempool
+    pub fn restore(mempool: &mut Mempool, transactions: Vec<Transaction>) {
+        for trx in transactions {
+            let _ = mempool.insert(trx);
+        }
+    }
+
+    //stage new utxos from outputs of coinbase and tranasactions*****************************************************
+    fn handle_outputs(&self, changes: &mut ChangeSet) {
         //generate new utxo for each unspents of outputs of coinbase of recieved block
         for unspent in &self.body.coinbase.output.unspents {
-            match UTXO::generate(
+            UTXO::generate(
                 self.header.number,
                 &self.body.coinbase.hash,
                 &self.body.coinbase.output.hash,
                 unspent,
-                db,
-                wallet,
-                window,
-            )
-            .await
-            {
-                Ok(_) => {}
-                Err(e) => {
-                    utxo_err.get_or_insert(e);
-                    break;
-                }
-            }
+                changes,
+            );
         }
 
         //generate new utxo for each unspents of outputs of transactiosn of recieved block
         for trx in &self.body.transactions {
             for unspent in &trx.output.unspents {
-                match UTXO::generate(
+                UTXO::generate(
                     self.header.number,
                     &trx.hash,
                     &trx.output.hash,
                     unspent,
-                    db,
-                    wallet,
-                    window,
-                )
-                .await
-                {
-                    Ok(_) => {}
-                    Err(e) => {
-                        utxo_err.get_or_insert(e);
-                        break;
-                    }
-                }
+                    changes,
+                );
             }
         }
-
-        match utxo_err {
-            None => Ok(()),
-            Some(e) => Err(e),
-        }
     }
 
-    //insert block to database
-    pub async fn insertion<'a>(self, db: &'a Database) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("Blocks");
-        let doc = to_document(&self).unwrap();
+    //insert block with its staged changes to database all together and then change last block
+    //remaining trxs of mempool are validated again with the new state
+    pub async fn insertion<'a, S: Storage>(
+        self,
+        db: &'a S,
+        changes: &ChangeSet,
+        mempool: &mut Mempool,
+        last_block: &mut Vec<Self>,
+        wallet: &Public,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
+        match db.commit(Some(&self), changes).await {
+            Ok(_) => {
+                last_block.clear();
+                last_block.push(self);
+                mempool.revalidate(db, window).await;
 
-        match collection.insert_one(doc).await {
-            Ok(_) => Ok(()),
-            Err(_) => Err("Error during insertion of block-(generation/block/block 249)"),
+                //update sum of centies if utxos of the wallet changed
+                if changes.touches(wallet) {
+                    match Transactions::sum_centies(db, wallet).await {
+                        Ok(sum) => Ok(window.emit("sum_centies", sum).unwrap()),
+                        Err(e) => Err(e),
+                    }
+                } else {
+                    Ok(())
+                }
+            }
+            Err(e) => Err(e),
         }
     }
 }
@@ -6,6 +6,7 @@ use serde_with::{serde_as, DisplayFromStr};
 use sp_core::ed25519::Public;
 
 use crate::generator::{
+    encoding::Canonical,
     relay::Relay,
     transaction::{Output, Transaction, Unspent},
     HashMaker, MerkelRoot,
@@ -37,6 +38,19 @@ pub struct Coinbase {
     validator_fee: Decimal,
 }
 
+impl Canonical for Coinbase {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hash.encode(out);
+        self.size.encode(out);
+        self.merkel.encode(out);
+        self.reward.encode(out);
+        self.output.encode(out);
+        self.fees.encode(out);
+        self.relay_fee.encode(out);
+        self.validator_fee.encode(out);
+    }
+}
+
 impl Coinbase {
     pub async fn new<'a>(
         transactions: &Vec<Transaction>,
@@ -121,7 +135,11 @@ impl Coinbase {
             for t in transactions {
                 trx_hashes.push(&t.hash);
             }
-            let merkel = MerkelRoot::make(trx_hashes).first().unwrap().clone();
+            //a block without transactions has "First" as merkel root like when it was made
+            let merkel = match MerkelRoot::make(trx_hashes).first() {
+                Some(root) => root.clone(),
+                None => "First".to_string(),
+            };
 
             //check merkel root that maked with coinbase merkel root to validation
             if merkel == self.merkel {
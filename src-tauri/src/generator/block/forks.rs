@@ -0,0 +1,409 @@
+use libp2p::PeerId;
+use tauri::Emitter;
+
+use crate::{
+    events::node::Node,
+    tools::{storage::Storage, undo::Undo},
+};
+
+use super::{attestation::Finality, block::Block, message::BlockMessage};
+
+//count of blocks that fork pool keeps
+const MAX_FORK_BLOCKS: usize = 100;
+//blocks deeper than this from the tip are not reorganized
+pub const MAX_REORG_DEPTH: u64 = 50;
+
+// Pool of valid-looking blocks that don't extend the tip of the chain (competing blocks and orphans)
+//
+// Fork choice rule: the longest chain wins, a branch replaces the current chain only when its tip is higher
+// than the stored tip and it doesn't revert a final block, so between branches with the same height the one that was seen first (the earliest
+// valid leader block) stays. Blocks of a branch are validated one by one while switching, and if one of them
+// is invalid the former chain is restored.
+#[derive(Debug)]
+pub struct ForkPool {
+    messages: Vec<BlockMessage>,
+}
+
+impl ForkPool {
+    pub fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+        }
+    }
+
+    // Keep a block message of a side chain (a full pool drops its lowest block)
+    // blocks that are not signed by the wallet of their registered validator are not kept
+    pub async fn insert<'a, S: Storage>(
+        &mut self,
+        db: &'a S,
+        message: BlockMessage,
+    ) -> Result<bool, &'a str> {
+        match Self::is_authored(db, &message.block).await {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        if self
+            .messages
+            .iter()
+            .any(|m| m.block.header.hash == message.block.header.hash)
+        {
+            return Ok(true);
+        }
+        if self.messages.len() >= MAX_FORK_BLOCKS {
+            if let Some(i) = self
+                .messages
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, m)| m.block.header.number)
+                .map(|(i, _)| i)
+            {
+                self.messages.remove(i);
+            }
+        }
+        self.messages.push(message);
+        Ok(true)
+    }
+
+    // Block is signed and its signer is the wallet of the registered validator that made it
+    async fn is_authored<'a, S: Storage>(db: &'a S, block: &Block) -> Result<bool, &'a str> {
+        if !block.check_signature() {
+            return Ok(false);
+        }
+        match db.find_validator(&block.header.validator).await {
+            Ok(Some(validator)) => Ok(validator.wallet == block.header.signature.key),
+            Ok(None) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Leader that the parent block elects for its child with the registry after the parent
+    // (the generator of the parent if no validator is in turn)
+    async fn expected_leader<'a, S: Storage>(db: &'a S, parent: &Block) -> Result<PeerId, &'a str> {
+        match db.validators().await {
+            Ok(validators) => {
+                Ok(BlockMessage::elect(parent, &validators).unwrap_or(parent.header.validator))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Every stored block in the range has an undo record, so the chain can be rewound to before it
+    // (blocks that came from a snapshot don't have undo records)
+    async fn revertable<'a, S: Storage>(db: &'a S, from: u64, to: u64) -> Result<bool, &'a str> {
+        for number in from..=to {
+            match db.has_undo(number).await {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    // Block of the pool at the same height as the block that is signed by the same validator
+    pub fn same_height(&self, block: &Block) -> Option<Block> {
+        self.messages
+            .iter()
+            .find(|m| {
+                m.block.header.number == block.header.number
+                    && m.block.header.validator == block.header.validator
+                    && m.block.header.hash != block.header.hash
+            })
+            .map(|m| m.block.clone())
+    }
+
+    // Remove blocks that are too deep to be reorganized
+    pub fn prune(&mut self, tip: u64) {
+        self.messages
+            .retain(|m| m.block.header.number.saturating_add(MAX_REORG_DEPTH) > tip);
+    }
+
+    // Find the best branch that connects to the stored chain and is longer than it
+    // blocks of the branch are returned from the fork point to its tip
+    pub async fn best_branch<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        tip: &Block,
+    ) -> Result<Option<Vec<BlockMessage>>, &'a str> {
+        //candidates are sorted by height and candidates with the same height keep the order they were seen
+        let mut candidates: Vec<&BlockMessage> = self
+            .messages
+            .iter()
+            .filter(|m| m.block.header.number > tip.header.number)
+            .collect();
+        candidates.sort_by(|a, b| b.block.header.number.cmp(&a.block.header.number));
+
+        //final blocks are never reverted
+        let finalized = match Finality::finalized(db).await {
+            Ok(finalized) => finalized,
+            Err(e) => return Err(e),
+        };
+
+        for candidate in candidates {
+            //walk back from the candidate through the pool
+            let mut branch = vec![candidate.clone()];
+            while let Some(parent) = self.messages.iter().find(|m| {
+                m.block.header.hash == branch[0].block.header.previous
+                    && m.block.header.number + 1 == branch[0].block.header.number
+            }) {
+                branch.insert(0, parent.clone());
+            }
+
+            //every block of the branch is made by the leader that its previous block named
+            if branch
+                .windows(2)
+                .any(|pair| pair[1].block.header.validator != pair[0].next_leader)
+            {
+                continue;
+            }
+
+            //first block of the branch must be a child of a stored block
+            let base = &branch[0].block.header;
+            let depth = (tip.header.number + 1).saturating_sub(base.number);
+            if base.number < 2 || depth > MAX_REORG_DEPTH || base.number <= finalized {
+                continue;
+            }
+            match db.find_block(base.number - 1).await {
+                Ok(Some(parent)) if parent.header.hash == base.previous => {
+                    //registry of the stored chain is after the tip, so the leader of a child of the tip is checked here
+                    //and the leader of a deeper fork point is checked by switch after rewinding the chain to it
+                    if parent.header.number == tip.header.number {
+                        match Self::expected_leader(db, &parent).await {
+                            Ok(leader) if leader == base.validator => {}
+                            Ok(_) => continue,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                Ok(_) => continue,
+                Err(e) => return Err(e),
+            }
+            match Self::revertable(db, base.number, tip.header.number).await {
+                Ok(true) => return Ok(Some(branch)),
+                Ok(false) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    // Switch the chain to the branch
+    // stored blocks after the fork point are reverted by their undo records and then blocks of the branch are inserted
+    pub async fn switch<'n, S: Storage>(
+        branch: Vec<BlockMessage>,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let (db, window, peerid, wallet) = (node.db, node.window, node.peerid, node.wallet);
+        let fork_point = branch[0].block.header.number - 1;
+
+        //nothing is reverted for a branch that has a block which is not signed by its validator
+        for message in &branch {
+            match Self::is_authored(db, &message.block).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    for message in &branch {
+                        node.forks.remove(&message.block.header.hash);
+                    }
+                    return Err("Branch has a block that is not signed by its validator-(generator/block/forks 208)");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        match Self::revertable(db, fork_point + 1, node.last_block[0].header.number).await {
+            Ok(true) => {}
+            Ok(false) => return Err(
+                "Blocks of the chain have no undo record to be reverted-(generator/block/forks 216)",
+            ),
+            Err(e) => return Err(e),
+        }
+
+        //keep blocks of the current chain for restoring it if the branch was invalid
+        let mut former = Vec::new();
+        for number in fork_point + 1..=node.last_block[0].header.number {
+            match db.find_block(number).await {
+                Ok(Some(block)) => former.push(block),
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        //a revert that failed in the middle leaves the chain at a lower block, so the former blocks above it are inserted again
+        if let Err(e) = Self::rewind(db, fork_point, node.last_block).await {
+            return match Self::restore(node, former).await {
+                Ok(_) => Err(e),
+                Err(e) => Err(e),
+            };
+        }
+
+        //registry is at the fork point now, so first block of the branch must be made by the leader that the fork point elects
+        let mut is_err = match Self::expected_leader(db, &node.last_block[0]).await {
+            Ok(leader) if leader == branch[0].block.header.validator => None,
+            Ok(_) => {
+                Some("Branch is not started by the elected leader-(generator/block/forks 243)")
+            }
+            Err(e) => Some(e),
+        };
+        for message in &branch {
+            if is_err.is_some() {
+                break;
+            }
+            match message
+                .block
+                .validation(
+                    node.last_block,
+                    db,
+                    node.mempool,
+                    window,
+                    node.turn,
+                    node.sync_state,
+                )
+                .await
+            {
+                Ok((block, changes)) => {
+                    if let Err(e) = message.check_next_leader(db, &changes).await {
+                        Block::restore(node.mempool, block.body.transactions);
+                        is_err.get_or_insert(e);
+                        break;
+                    }
+                    if let Err(e) = block
+                        .insertion(db, &changes, node.mempool, node.last_block, wallet, window)
+                        .await
+                    {
+                        is_err.get_or_insert(e);
+                        break;
+                    }
+                }
+                Err(e) => {
+                    is_err.get_or_insert(e.message());
+                    break;
+                }
+            }
+        }
+
+        match is_err {
+            //branch was invalid so it is dropped and the former chain is inserted again
+            Some(e) => {
+                for message in &branch {
+                    node.forks.remove(&message.block.header.hash);
+                }
+                let rewound = Self::rewind(db, fork_point, node.last_block).await;
+                match Self::restore(node, former).await {
+                    Ok(_) => match rewound {
+                        Ok(_) => Err(e),
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                }
+            }
+
+            //transactions of reverted blocks that are not in the branch return to mempool
+            None => {
+                for message in &branch {
+                    node.forks.remove(&message.block.header.hash);
+                }
+                for block in former {
+                    Block::restore(node.mempool, block.body.transactions);
+                }
+                node.mempool.revalidate(db, window).await;
+
+                //leader and turn follow the tip of the new chain
+                let next_leader = branch.last().unwrap().next_leader;
+                if &next_leader == peerid {
+                    node.leader.update(None, window);
+                    node.turn.on(window);
+                } else {
+                    node.leader.update(Some(next_leader), window);
+                    if let Ok(Some(validator)) = db.find_validator(peerid).await {
+                        node.turn.off(validator.waiting as u16, window);
+                    }
+                }
+                window
+                    .emit(
+                        "status",
+                        format!("Chain reorganized from block {}", fork_point + 1),
+                    )
+                    .unwrap();
+                Ok(())
+            }
+        }
+    }
+
+    // Insert blocks of the former chain that are above the stored tip again
+    async fn restore<'n, S: Storage>(
+        node: &mut Node<'n, S>,
+        former: Vec<Block>,
+    ) -> Result<(), &'n str> {
+        let (db, window) = (node.db, node.window);
+        match db.last_block().await {
+            Ok(Some(block)) => {
+                node.last_block.clear();
+                node.last_block.push(block);
+            }
+            Ok(None) => {
+                return Err("There is no block for restoring the chain-(generator/block/forks 344)")
+            }
+            Err(e) => return Err(e),
+        }
+        for block in former {
+            if block.header.number <= node.last_block[0].header.number {
+                continue;
+            }
+            match block
+                .validation(
+                    node.last_block,
+                    db,
+                    node.mempool,
+                    window,
+                    node.turn,
+                    node.sync_state,
+                )
+                .await
+            {
+                Ok((block, changes)) => {
+                    if let Err(e) = block
+                        .insertion(
+                            db,
+                            &changes,
+                            node.mempool,
+                            node.last_block,
+                            node.wallet,
+                            window,
+                        )
+                        .await
+                    {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e.message()),
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, hash: &str) {
+        self.messages.retain(|m| m.block.header.hash != hash);
+    }
+
+    // Revert stored chain to the height and set last block
+    async fn rewind<'a, S: Storage>(
+        db: &'a S,
+        height: u64,
+        last_block: &mut Vec<Block>,
+    ) -> Result<(), &'a str> {
+        match Undo::revert_to(db, height).await {
+            Ok(_) => match db.last_block().await {
+                Ok(Some(block)) => {
+                    last_block.clear();
+                    last_block.push(block);
+                    Ok(())
+                }
+                Ok(None) => {
+                    Err("There is no block after reverting the chain-(generator/block/forks 402)")
+                }
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
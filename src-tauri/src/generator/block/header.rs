@@ -1,22 +1,36 @@
 use centichain_keypair::CentichainKey;
-use chrono::{SubsecRound, Utc};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
 use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
-use sp_core::ed25519::{Public, Signature};
+use sp_core::{
+    ed25519::{Public, Signature},
+    Pair,
+};
 
-use crate::generator::{HashMaker, MerkelRoot};
+use crate::generator::{encoding::Canonical, merkle::MerkleTree, HashMaker, MerkelRoot};
 
 use super::block::{Block, Body};
 
+// Version of the header format that new blocks have
+// 0: signature is over hash of the body (blocks before versioning)
+// 1: hash of the header commits to every header field and hash of the body, and signature is over it
+// 2: like 1 but hashes are made from the canonical encoding (see generator/encoding)
+// 3: like 2 and merkel is the root of the merkle tree of coinbase and transactions (see generator/merkle)
+pub const HEADER_VERSION: u32 = 3;
+
 // Define the structure of a block's header with signature.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Header {
+    #[serde(default)]
+    pub version: u32,
     pub number: u64,
     pub hash: String,
     pub previous: String,
     pub validator: PeerId,
     pub relay: PeerId,
     merkel: String,
+    #[serde(default)]
+    body_hash: String,
     pub signature: Sign,
     date: String,
 }
@@ -29,6 +43,14 @@ pub struct Sign {
 }
 
 impl Header {
+    // Time that the block was made
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        match NaiveDateTime::parse_from_str(&self.date, "%Y-%m-%d %H:%M:%S UTC") {
+            Ok(time) => Some(time.and_utc()),
+            Err(_) => None,
+        }
+    }
+
     pub async fn new<'a>(
         wallet: &Public,
         body: &Body,
@@ -38,61 +60,147 @@ impl Header {
         last_block: &mut Vec<Block>,
     ) -> Result<Self, &'a str> {
         //define fields
-        let number: u64;
-        let hash_data = serde_json::to_string(body).unwrap();
-        let hash = HashMaker::generate(&hash_data);
-        let mut previous = String::new();
-        let trx_hashes = vec![&body.coinbase.hash, &body.coinbase.merkel];
-        let merkel = MerkelRoot::make(trx_hashes).first().unwrap().clone();
-
-        if last_block.len() > 0 {
-            number = last_block[0].header.number + 1;
-            previous.push_str(&last_block[0].header.hash);
-            let sign_block = CentichainKey::signing(private, &hash);
-            match sign_block {
-                Ok(signed) => {
-                    let signature = Sign {
-                        signatgure: signed,
-                        key: *wallet,
-                    };
-                    let date = Utc::now().round_subsecs(0).to_string();
-                    Ok(Self {
-                        number,
-                        hash,
-                        previous,
-                        validator: *peerid,
-                        relay: *relay_id,
-                        merkel,
-                        signature,
-                        date,
-                    })
-                }
-                Err(_e) => Err("Error during signing-(generator/header 82)"),
-            }
+        let body_hash = Self::body_hash(body, HEADER_VERSION);
+        let merkel = Self::merkel(body, HEADER_VERSION);
+        let date = Utc::now().round_subsecs(0).to_string();
+
+        //first block of the chain is the genesis block
+        let (number, previous) = if last_block.len() > 0 {
+            (
+                last_block[0].header.number + 1,
+                last_block[0].header.hash.clone(),
+            )
         } else {
-            number = 1;
-            previous.push_str("This Is The Genesis Block");
-            let sign_block = CentichainKey::signing(private, &hash);
-            match sign_block {
-                Ok(signed) => {
-                    let signature = Sign {
-                        signatgure: signed,
-                        key: *wallet,
-                    };
-                    let date = Utc::now().round_subsecs(0).to_string();
-                    Ok(Self {
-                        number,
-                        hash,
-                        previous,
-                        validator: *peerid,
-                        relay: *relay_id,
-                        merkel,
-                        signature,
-                        date,
-                    })
-                }
-                Err(_e) => Err("Error during signing-(generator/header 94)"),
+            (1, "This Is The Genesis Block".to_string())
+        };
+
+        let mut header = Self {
+            version: HEADER_VERSION,
+            number,
+            hash: String::new(),
+            previous,
+            validator: *peerid,
+            relay: *relay_id,
+            merkel,
+            body_hash,
+            signature: Sign {
+                signatgure: Signature::from_raw([0; 64]),
+                key: *wallet,
+            },
+            date,
+        };
+        header.hash = header.header_hash();
+
+        //signature is over hash of the header so no field of it can be changed
+        match CentichainKey::signing(private, &header.hash) {
+            Ok(signed) => {
+                header.signature.signatgure = signed;
+                Ok(header)
             }
+            Err(_e) => Err("Error during signing-(generator/header 99)"),
         }
     }
+
+    // Merkel of the header commits to every transaction of the body from version 3
+    fn merkel(body: &Body, version: u32) -> String {
+        match version {
+            0..=2 => {
+                let trx_hashes = vec![&body.coinbase.hash, &body.coinbase.merkel];
+                MerkelRoot::make(trx_hashes).first().unwrap().clone()
+            }
+            _ => MerkleTree::new(body.leaves()).root(),
+        }
+    }
+
+    // Root of the merkle tree of the block (only headers from version 3 have it)
+    pub fn merkle_root(&self) -> Option<&String> {
+        match self.version {
+            0..=2 => None,
+            _ => Some(&self.merkel),
+        }
+    }
+
+    fn body_hash(body: &Body, version: u32) -> String {
+        match version {
+            0 | 1 => HashMaker::generate(&serde_json::to_string(body).unwrap()),
+            _ => body.canonical_hash("body"),
+        }
+    }
+
+    // Hash of every field of the header except the hash and signature
+    fn header_hash(&self) -> String {
+        match self.version {
+            1 => HashMaker::generate(&format!(
+                "{}{}{}{}{}{}{}{}",
+                self.version,
+                self.number,
+                self.previous,
+                self.validator,
+                self.relay,
+                self.merkel,
+                self.body_hash,
+                self.date
+            )),
+            _ => self.canonical_hash("header"),
+        }
+    }
+
+    // Check hash and signature of the header with the body of its block
+    pub fn verify(&self, body: &Body) -> bool {
+        match self.version {
+            0 => sp_core::ed25519::Pair::verify(
+                &self.signature.signatgure,
+                Self::body_hash(body, 0),
+                &self.signature.key,
+            ),
+            1..=3 => {
+                self.merkel == Self::merkel(body, self.version)
+                    && self.body_hash == Self::body_hash(body, self.version)
+                    && self.check_signature()
+            }
+            _ => false,
+        }
+    }
+
+    // New blocks must have the current version, blocks of former versions are accepted only
+    // at or below the trusted height (the stored chain or a snapshot)
+    pub fn check_version(&self, trusted: u64) -> bool {
+        self.version >= HEADER_VERSION || self.number <= trusted
+    }
+
+    // Check hash and signature of the header without its body (headers before version 1 sign the body)
+    pub fn check_signature(&self) -> bool {
+        match self.version {
+            1..=3 => {
+                self.hash == self.header_hash()
+                    && sp_core::ed25519::Pair::verify(
+                        &self.signature.signatgure,
+                        &self.hash,
+                        &self.signature.key,
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+// Fields of the header that its hash commits to (hash and signature are not included)
+impl Canonical for Header {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.version.encode(out);
+        self.number.encode(out);
+        self.previous.encode(out);
+        self.validator.encode(out);
+        self.relay.encode(out);
+        self.merkel.encode(out);
+        self.body_hash.encode(out);
+        self.date.encode(out);
+    }
+}
+
+impl Canonical for Sign {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.signatgure.encode(out);
+        self.key.encode(out);
+    }
 }
@@ -1,29 +1,28 @@
-use libp2p::{futures::StreamExt, PeerId, Swarm};
-use mongodb::{
-    bson::{doc, from_document, Document},
-    Collection, Database,
-};
-use rand::seq::IteratorRandom;
+use libp2p::{PeerId, Swarm};
 use serde::{Deserialize, Serialize};
 use sp_core::ed25519::Public;
 use tauri::Emitter;
 
 use crate::{
-    events::handshaking::Requests,
+    events::{handshaking::Requests, node::Node},
     generator::{
         leader::Leader,
+        mempool::Mempool,
         relay::Relay,
         swarm::{CentichainBehaviour, Req},
-        transaction::Transaction,
         validator::Validator,
+        HashMaker,
     },
     tools::{
+        changeset::ChangeSet,
+        evidence::{Evidence, Offence},
+        invalid::Invalid,
+        storage::Storage,
         trun_sync::{Sync, Turn},
-        wrongdoer::WrongDoer,
     },
 };
 
-use super::block::Block;
+use super::{attestation::Finality, block::Block, forks::ForkPool};
 
 // Struct representing a block message in the network
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -34,9 +33,9 @@ pub struct BlockMessage {
 
 impl BlockMessage {
     // Create a new gossip message for propagation to the network
-    pub async fn new<'a>(
-        db: &'a Database,
-        transactions: Vec<Transaction>,
+    pub async fn new<'a, S: Storage>(
+        db: &'a S,
+        mempool: &mut Mempool,
         wallet: &Public,
         peerid: &PeerId,
         private: &String,
@@ -48,164 +47,334 @@ impl BlockMessage {
         sync_state: &mut Sync,
     ) -> Result<Self, &'a str> {
         match Block::new(
-            &db,
-            transactions,
-            wallet,
-            peerid,
-            private,
-            last_block,
-            relay,
-            window,
-            turn,
-            sync_state,
+            db, mempool, wallet, peerid, private, last_block, relay, window, turn, sync_state,
         )
         .await
         {
-            Ok(block) => match Self::find_next_leader(db, *peerid, turn, leader, window).await {
-                Ok(next_leader) => {
-                    leader.peerid.get_or_insert(next_leader); // Set leader peer id
-                    window.emit("block", "+").unwrap(); // Show new block in front-end
-                    window.emit("genBlock", "New block is created").unwrap();
-                    Ok(Self { block, next_leader }) // Return block message
+            Ok(block) => {
+                match Self::find_next_leader(db, &block, *peerid, turn, leader, window).await {
+                    Ok(next_leader) => {
+                        leader.peerid.get_or_insert(next_leader); // Set leader peer id
+                        window.emit("block", "+").unwrap(); // Show new block in front-end
+                        window.emit("genBlock", "New block is created").unwrap();
+                        Ok(Self { block, next_leader }) // Return block message
+                    }
+                    Err(e) => Err(e),
                 }
-                Err(e) => Err(e),
-            },
+            }
             Err(e) => Err(e),
         }
     }
 
     // Handle received block messages
-    pub async fn handle<'a>(
+    pub async fn handle<'n, S: Storage>(
         self,
-        swarm: &mut Swarm<CentichainBehaviour>,
-        peerid: &PeerId,
-        db: &'a Database,
-        recvied_blocks: &mut Vec<Self>,
-        sync_state: &mut Sync,
-        last_block: &mut Vec<Block>,
-        mempool: &mut Vec<Transaction>,
-        leader: &mut Leader,
         source: PeerId,
-        relay: &mut Relay,
-        window: &tauri::Window,
-        turn: &mut Turn,
-        wallet: &Public,
-    ) -> Result<(), &'a str> {
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let db = node.db;
+        let window = node.window;
+        let (peerid, wallet, private) = (node.peerid, node.wallet, node.private);
+
         if self.block.header.signature.key != *wallet {
+            // Headers of former versions are only accepted for blocks of the stored chain
+            let stored = node
+                .last_block
+                .first()
+                .map(|b| b.header.number)
+                .unwrap_or(0);
+            if !self.block.header.check_version(stored) {
+                return Ok(window
+                    .emit("status", "Block header version is old and Block rejected.")
+                    .unwrap());
+            }
+
+            // Block that doesn't extend the tip goes to fork pool and chain switches if there is a longer branch
+            if let Sync::Synced = node.sync_state {
+                if node.last_block.len() > 0
+                    && self.block.header.previous != node.last_block[0].header.hash
+                {
+                    return self.fork(node).await;
+                }
+            }
+
             // If leader is true then validate block
-            if leader.peerid.is_none() || self.block.header.validator == leader.peerid.unwrap() {
-                match sync_state {
+            if node.leader.peerid.is_none()
+                || self.block.header.validator == node.leader.peerid.unwrap()
+            {
+                match node.sync_state {
                     // If validator is synced then validate block
                     Sync::Synced => match self
                         .block
-                        .validation(last_block, db, mempool, wallet, window, turn, sync_state)
+                        .validation(
+                            node.last_block,
+                            db,
+                            node.mempool,
+                            window,
+                            node.turn,
+                            node.sync_state,
+                        )
                         .await
                     {
-                        Ok(_) => {
-                            window.emit("mempool", mempool.clone()).unwrap(); // Show mempool in front-end
+                        Ok((block, changes)) => {
+                            // Next leader must be the validator that every node elects after this block
+                            if let Err(e) = self.check_next_leader(db, &changes).await {
+                                Block::restore(node.mempool, block.body.transactions);
+                                window.emit("status", e).unwrap();
+                                return node
+                                    .leader
+                                    .start_voting(
+                                        db,
+                                        node.swarm,
+                                        peerid,
+                                        wallet,
+                                        private,
+                                        window,
+                                        node.turn,
+                                        node.sync_state,
+                                    )
+                                    .await;
+                            }
+
+                            window
+                                .emit("node.mempool", node.mempool.transactions())
+                                .unwrap(); // Show mempool in front-end
                             window.emit("block", "+").unwrap(); // Show new block in front-end
 
-                            match self.block.insertion(db).await {
+                            match block
+                                .insertion(db, &changes, node.mempool, node.last_block, wallet, window)
+                                .await
+                            {
                             Ok(_) => {
+                                // Attest the block so it can become final
+                                if let Err(e) = Finality::attest(&self.block, node).await {
+                                    window.emit("error", e).unwrap();
+                                }
+
                                 // If next leader was peer id, turn on and update leader
                                 if &self.next_leader == peerid {
-                                    leader.update(None, window);
-                                    turn.on(window);
+                                    node.leader.update(None, window);
+                                    node.turn.on(window);
                                     Ok(())
                                 } else {
-                                    Ok(leader.update(Some(self.next_leader), window)) // If block was valid and inserted to DB then change leader 
+                                    Ok(node.leader.update(Some(self.next_leader), window)) // If block was valid and inserted to DB then change leader 
                                 }
                             }
-                            Err(_) => Err("Error while inserting new block to database-(generator/block/message 101)")
+                            Err(_) => Err("Error while inserting new block to database-(generator/block/message 163)")
                         }
                         }
-                        Err(e) => {
-                            if source == relay.peerid.unwrap() {
+                        // A storage error is a problem of this node, not of the block
+                        Err(Invalid::Storage(e)) => Err(e),
+                        Err(Invalid::Rejected(e)) => {
+                            // A signed block that is not valid is an evidence against its generator
+                            if self.block.check_signature() {
+                                let evidence = Evidence::new(
+                                    Offence::InvalidBlock {
+                                        block: self.block.clone(),
+                                    },
+                                    peerid,
+                                );
+                                match evidence.report(node).await {
+                                    Ok(_) => {}
+                                    Err(e) => return Err(e),
+                                }
+                            }
+
+                            if source == node.relay.peerid.unwrap() {
                                 window.emit("error", e).unwrap();
-                                swarm.disconnect_peer_id(relay.peerid.unwrap()).unwrap(); // If message source was validator's relay then connection must be disconnected
+                                node.swarm
+                                    .disconnect_peer_id(node.relay.peerid.unwrap())
+                                    .unwrap(); // If message source was validator's relay then connection must be disconnected
                                 Ok(())
                             } else {
                                 window.emit("status", e).unwrap();
-                                leader
-                                    .start_voting(db, swarm, peerid, window, turn, sync_state)
+                                node.leader
+                                    .start_voting(
+                                        db,
+                                        node.swarm,
+                                        peerid,
+                                        wallet,
+                                        private,
+                                        window,
+                                        node.turn,
+                                        node.sync_state,
+                                    )
                                     .await
                                 // If message source was not validator's relay then voting of leader must be started
                             }
                         }
                     },
                     // If validator is not synced, received message is pushed to received blocks for syncing
-                    Sync::NotSynced => Ok(recvied_blocks.push(self)),
+                    Sync::NotSynced => Ok(node.recieved_blocks.push(self)),
                 }
+            } else if self.block.check_signature() {
+                // Block of a validator that is not the leader is an evidence against it
+                let evidence = Evidence::new(
+                    Offence::WrongLeader {
+                        block: self.block,
+                        leader: node.leader.peerid.unwrap(),
+                    },
+                    peerid,
+                );
+                evidence.report(node).await
             } else {
-                match WrongDoer::remove(db, self.block.header.validator, turn, sync_state, window)
-                    .await
+                // Nobody can be blamed for a block that its signature is wrong
+                Ok(window
+                    .emit("status", "Block signature is wrong and Block rejected.")
+                    .unwrap())
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    // Keep the block in fork pool and switch to the best branch if it is longer than the chain
+    async fn fork<'n, S: Storage>(self, node: &mut Node<'n, S>) -> Result<(), &'n str> {
+        let db = node.db;
+        let window = node.window;
+        let peerid = node.peerid;
+
+        // Another block of the validator at the same height (stored or in fork pool) is double signing
+        if self.block.check_signature() {
+            let first = match db.find_block(self.block.header.number).await {
+                Ok(Some(stored))
+                    if stored.header.validator == self.block.header.validator
+                        && stored.header.hash != self.block.header.hash =>
                 {
-                    Ok(peer) => Ok(window
-                        .emit("status", format!("This wrongdoer removed: {}", peer))
-                        .unwrap()),
-                    Err(e) => Err(e),
+                    Some(stored)
                 }
+                Ok(_) => node.forks.same_height(&self.block),
+                Err(e) => return Err(e),
+            };
+            if let Some(first) = first {
+                let evidence = Evidence::new(
+                    Offence::DoubleSign {
+                        first,
+                        second: self.block,
+                    },
+                    peerid,
+                );
+                return evidence.report(node).await;
             }
+        }
+
+        match node.forks.insert(db, self).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(window
+                    .emit(
+                        "status",
+                        "Block is not signed by its validator and rejected.",
+                    )
+                    .unwrap())
+            }
+            Err(e) => return Err(e),
+        }
+        node.forks.prune(node.last_block[0].header.number);
+        match node.forks.best_branch(db, &node.last_block[0]).await {
+            Ok(Some(branch)) => {
+                match ForkPool::switch(branch, node).await {
+                    Ok(_) => Ok(()),
+                    // An invalid branch is not a problem of this node
+                    Err(e) => Ok(window.emit("status", e).unwrap()),
+                }
+            }
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Elect the next leader from validators that their waiting is 0 and are not paused
+    // it is a function of the previous block hash of the block and the validators registry so every node gets the same leader
+    // (previous hash is used because the block generator can't change it to choose a friend)
+    pub fn elect(block: &Block, validators: &Vec<Validator>) -> Option<PeerId> {
+        let mut in_turn_validators: Vec<PeerId> = validators
+            .iter()
+            .filter(|v| v.waiting == 0 && !v.paused)
+            .map(|v| v.peerid)
+            .collect();
+
+        if in_turn_validators.len() > 0 {
+            // Sort them because order of the registry in databases of validators can be different
+            in_turn_validators.sort_by_key(|peerid| peerid.to_string());
+            let seed = HashMaker::generate(&block.header.previous);
+            let index =
+                u64::from_str_radix(&seed[..16], 16).unwrap() % in_turn_validators.len() as u64;
+            Some(in_turn_validators[index as usize])
         } else {
-            Ok(())
+            None
+        }
+    }
+
+    // Check next leader of the message by electing it again with the registry that the block makes
+    pub async fn check_next_leader<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        changes: &ChangeSet,
+    ) -> Result<(), &'a str> {
+        match db.validators().await {
+            Ok(mut validators) => {
+                // Apply staged waiting of validators
+                for validator in validators.iter_mut() {
+                    if let Some(staged) = changes
+                        .validators
+                        .iter()
+                        .find(|v| v.peerid == validator.peerid)
+                    {
+                        *validator = staged.clone();
+                    }
+                }
+
+                // If there is no validator in turn then the block generator is the next leader
+                let elected =
+                    Self::elect(&self.block, &validators).unwrap_or(self.block.header.validator);
+                if elected == self.next_leader {
+                    Ok(())
+                } else {
+                    Err("Block rejected, next leader is not the elected validator.")
+                }
+            }
+            Err(_) => Err("Database error while getting validators-(generator/block/message 337)"),
         }
     }
 
     // Find next leader and return it
-    pub async fn find_next_leader<'a>(
-        db: &'a Database,
+    pub async fn find_next_leader<'a, S: Storage>(
+        db: &'a S,
+        block: &Block,
         peerid: PeerId,
         turn: &mut Turn,
         leader: &mut Leader,
         window: &tauri::Window,
     ) -> Result<PeerId, &'a str> {
-        let mut in_turn_validators = Vec::new(); // Define it for get validators that their waiting is 0 and push to it
-        let collection: Collection<Document> = db.collection("validators");
-
-        // Get count of validators that their waiting is 0 and if there is not return current leader peer id
-        match collection.count_documents(doc! {"waiting": 0}).await {
-            Ok(count) => {
-                if count > 0 {
-                    match collection.find(doc! {"waiting": 0}).await {
-                        Ok(mut cursor) => {
-                            while let Some(Ok(doc)) = cursor.next().await {
-                                let validator: Validator = from_document(doc).unwrap();
-                                in_turn_validators.push(validator); // Push validator that its waiting is 0 to in turns
-                            }
-
-                            // Choose a validator at random
-                            let rnd_validator = in_turn_validators
-                                .iter()
-                                .choose(&mut rand::thread_rng())
-                                .unwrap();
+        match db.validators().await {
+            Ok(validators) => {
+                // Elect one of validators that their waiting is 0 and if there is not return current leader peer id
+                match Self::elect(block, &validators) {
+                    Some(elected) => {
+                        turn.off(validators.len() as u16, window); // Turn off and set waiting of relay to count of validators
 
-                            let count_of_validators =
-                                collection.count_documents(doc! {}).await.unwrap();
-                            turn.off(count_of_validators as u16, window); // Turn off and set waiting of relay to count of validators
+                        // Update leader
+                        leader.update(Some(elected), window);
 
-                            // Update leader
-                            leader.update(Some(rnd_validator.peerid), window);
-
-                            Ok(rnd_validator.peerid) // Return random validator
-                        }
-                        Err(_) => Err("Error while finding in turn validators"),
+                        Ok(elected) // Return elected validator
                     }
-                } else {
-                    // Turning on and leader updates
-                    turn.on(window);
-                    leader.update(None, window);
+                    None => {
+                        // Turning on and leader updates
+                        turn.on(window);
+                        leader.update(None, window);
 
-                    Ok(peerid) // Return our peer id as next leader
+                        Ok(peerid) // Return our peer id as next leader
+                    }
                 }
             }
-            Err(_) => Err("Database error while getting count-(generator/block/message 175)"),
+            Err(_) => Err("Database error while getting count-(generator/block/message 371)"),
         }
     }
 
-    // Post the block message to the network
-    pub async fn post<'a>(
+    // Post the block message to the network (block is inserted to database when it was made)
+    pub fn post<'a>(
         self,
-        db: &'a Database,
         swarm: &mut Swarm<CentichainBehaviour>,
         relay: &Relay,
     ) -> Result<(), &'a str> {
@@ -219,6 +388,6 @@ impl BlockMessage {
             .reqres
             .send_request(&relay.peerid.unwrap(), request); // Send new request to relay
 
-        self.block.insertion(db).await // Insert block to database after sending it
+        Ok(())
     }
 }
@@ -1,5 +1,8 @@
+pub mod attestation;
 pub mod block;
 pub mod coinbase;
+pub mod forks;
 pub mod header;
 pub mod message;
+pub mod production;
 pub mod reward;
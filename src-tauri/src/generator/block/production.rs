@@ -0,0 +1,110 @@
+use chrono::{Duration, Utc};
+use tauri::Emitter;
+
+use crate::{
+    events::node::Node,
+    generator::{leader::LeaderTime, mempool::Mempool},
+    tools::{settings::Settings, storage::Storage, trun_sync::Sync},
+};
+
+use super::{
+    block::{Block, MAX_TRANSACTIONS},
+    message::BlockMessage,
+};
+
+// Policy of sealing blocks by the validator in its turn
+// a block is sealed when mempool has enough transactions for a full block or its oldest transaction waited enough
+// and if heartbeat is on an empty block is sealed when there was no block for heartbeat time
+#[derive(Debug)]
+pub struct Production {
+    pub max_transactions: usize,
+    max_wait: Duration,
+    heartbeat: Option<Duration>,
+}
+
+impl Production {
+    pub fn new() -> Self {
+        let settings = Settings::load();
+        Self {
+            max_transactions: settings.block_max_transactions.clamp(1, MAX_TRANSACTIONS),
+            max_wait: Duration::seconds(settings.block_max_wait as i64),
+            heartbeat: match settings.heartbeat_interval {
+                0 => None,
+                secs => Some(Duration::seconds(secs as i64)),
+            },
+        }
+    }
+
+    // Check if a block must be sealed now
+    pub fn should_seal(&self, mempool: &Mempool, last_block: &[Block]) -> bool {
+        let now = Utc::now();
+        if mempool.len() >= self.max_transactions {
+            true
+        } else if let Some(oldest) = mempool.oldest() {
+            now - oldest >= self.max_wait
+        } else {
+            match (
+                self.heartbeat,
+                last_block.first().and_then(|b| b.header.time()),
+            ) {
+                (Some(heartbeat), Some(time)) => now - time >= heartbeat,
+                _ => false,
+            }
+        }
+    }
+
+    // Scheduled check of the policy
+    // in turn of the validator it seals a block if it must and for other validators it starts timer of the leader
+    // when a block is expected (there are transactions or heartbeat passed since the last block) so a dead leader will be replaced
+    pub async fn tick<'n, S: Storage>(&self, node: &mut Node<'n, S>) -> Result<(), &'n str> {
+        match node.sync_state {
+            Sync::Synced if !node.leader.in_check && !node.last_block.is_empty() => {
+                if node.turn.shift {
+                    if self.should_seal(node.mempool, node.last_block) {
+                        match BlockMessage::new(
+                            node.db,
+                            node.mempool,
+                            node.wallet,
+                            node.peerid,
+                            node.private,
+                            node.last_block,
+                            node.relay,
+                            node.turn,
+                            node.leader,
+                            node.window,
+                            node.sync_state,
+                        )
+                        .await
+                        {
+                            Ok(block_message) => {
+                                node.window
+                                    .emit("mempool", node.mempool.transactions())
+                                    .unwrap();
+                                block_message.post(node.swarm, node.relay)
+                            }
+                            Err(e) => Err(e),
+                        }
+                    } else {
+                        Ok(())
+                    }
+                } else {
+                    if node.leader.peerid.is_some() && node.leader.timer == LeaderTime::Off {
+                        if node.mempool.len() > 0 {
+                            node.leader.timer_start();
+                        } else if let (Some(heartbeat), Some(time)) = (
+                            self.heartbeat,
+                            node.last_block.first().and_then(|b| b.header.time()),
+                        ) {
+                            //an idle leader seals its empty block heartbeat after the last block
+                            if Utc::now() - time >= heartbeat {
+                                node.leader.timer_start_from(time + heartbeat);
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use libp2p::PeerId;
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
+use sp_core::ed25519::{Public, Signature};
+
+// Canonical byte encoding of consensus structures that hashes and signatures are made from
+// it doesn't depend on serde, so field order, decimal formatting or peer id encoding of a
+// serializer can't change hashes (rules and test vectors are in docs/encoding.md)
+//
+// u8, u32, u64: fixed width big endian (usize is encoded as u64)
+// String: u32 length of its utf-8 bytes and then the bytes
+// Decimal: String of the normalized decimal (no trailing zeros, "0" for zero)
+// Public, Signature: raw 32 and 64 bytes
+// PeerId: u32 length of its multihash bytes and then the bytes
+// Vec: u32 count of items and then the items
+// struct: its fields in the order of the docs
+pub trait Canonical {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    // Hex of sha256 of the tag and the value, the tag separates hashes of different structures
+    fn canonical_hash(&self, tag: &str) -> String {
+        let mut out = Vec::new();
+        tag.encode(&mut out);
+        self.encode(&mut out);
+        let mut hasher = Sha256::new();
+        hasher.update(out);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl Canonical for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Canonical for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl Canonical for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl Canonical for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+}
+
+impl Canonical for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Canonical for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Canonical for Decimal {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.normalize().to_string().encode(out);
+    }
+}
+
+impl Canonical for Public {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_ref());
+    }
+}
+
+impl Canonical for Signature {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_ref());
+    }
+}
+
+impl Canonical for PeerId {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let bytes = self.to_bytes();
+        (bytes.len() as u32).encode(out);
+        out.extend_from_slice(&bytes);
+    }
+}
+
+impl<T: Canonical> Canonical for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use libp2p::{identity::Keypair, PeerId};
+    use rust_decimal::Decimal;
+    use sp_core::ed25519::{Public, Signature};
+
+    use crate::{
+        events::syncing::VSync,
+        generator::{
+            block::{attestation::Attestation, header::Header, header::Sign},
+            membership::{Action, Membership},
+            merkle::MerkleTree,
+            outnode::Outnode,
+            transaction::{Unspent, UnspentData},
+            vote::Vote,
+            MerkelRoot,
+        },
+        tools::utxo::UTXO,
+    };
+
+    use super::Canonical;
+
+    // Keys of the vectors in docs/encoding.md
+    fn wallet() -> Public {
+        Public::from_raw([1; 32])
+    }
+
+    fn peer(seed: u8) -> PeerId {
+        Keypair::ed25519_from_bytes([seed; 32])
+            .unwrap()
+            .public()
+            .to_peer_id()
+    }
+
+    fn sign() -> Sign {
+        Sign {
+            signatgure: Signature::from_raw([0; 64]),
+            key: wallet(),
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Bytes and hash of the value with the tag must be the ones of the docs
+    fn check<T: Canonical>(value: &T, tag: &str, bytes: &str, hash: &str) {
+        let mut out = Vec::new();
+        tag.encode(&mut out);
+        value.encode(&mut out);
+        assert_eq!(hex(&out), bytes, "bytes of {}", tag);
+        assert_eq!(value.canonical_hash(tag), hash, "hash of {}", tag);
+    }
+
+    fn unspent() -> Unspent {
+        let data = UnspentData {
+            wallet: wallet(),
+            salt: 7,
+            value: Decimal::from_str("12.500").unwrap(),
+        };
+        Unspent {
+            hash: data.canonical_hash("unspent"),
+            data,
+        }
+    }
+
+    fn utxos() -> Vec<UTXO> {
+        vec![UTXO {
+            block: 42,
+            trx_hash: "a".repeat(64),
+            output_hash: "b".repeat(64),
+            unspent_hash: "c".repeat(64),
+            unspent: Decimal::from_str("13.0").unwrap(),
+        }]
+    }
+
+    #[test]
+    fn keys_of_vectors() {
+        assert_eq!(
+            wallet().to_string(),
+            "5C62Ck4UrFPiBtoCmeSrgF7x9yv9mn38446dhCpsi2mLHiFT"
+        );
+        assert_eq!(
+            peer(2).to_string(),
+            "12D3KooWJWoaqZhDaoEFshF7Rh1bpY9ohihFhzcW6d69Lr2NASuq"
+        );
+        assert_eq!(
+            peer(3).to_string(),
+            "12D3KooWRndVhVZPCiQwHBBBdg769GyrPUW13zxwqQyf9r3ANaba"
+        );
+    }
+
+    #[test]
+    fn transaction_vectors() {
+        check(
+            &unspent().data,
+            "unspent",
+            "00000007756e7370656e740101010101010101010101010101010101010101010101010101010101010101000000070000000431322e35",
+            "50fa6310563ea0386d872ebdbe0d9adf70569f93db9c3a999b47066aeb0f227a",
+        );
+        check(
+            &vec![unspent()],
+            "output",
+            "000000066f75747075740000000100000040353066613633313035363365613033383664383732656264626530643961646637303536396639336462396333613939396234373036366165623066323237610101010101010101010101010101010101010101010101010101010101010101000000070000000431322e35",
+            "04b6de0da637c5b187bff920652b9b032dd851fff7bfd63f81067c19aed8f840",
+        );
+        check(
+            &utxos(),
+            "input",
+            "00000005696e70757400000001000000000000002a000000406161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616161616100000040626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262626262620000004063636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363636363000000023133",
+            "35808c5a42f6f535cf09475235e2d23bce25d4d8882be81c82cfb8600998a30a",
+        );
+
+        let input_hash = utxos().canonical_hash("input");
+        let output_hash = vec![unspent()].canonical_hash("output");
+        assert_eq!(
+            MerkelRoot::make(vec![&input_hash, &output_hash])[0],
+            "b757fd7aa9df1d27b6040da598b1b5b71680f2cd12269ed8e6f85b454a0b2159"
+        );
+    }
+
+    #[test]
+    fn header_vector() {
+        let header: Header = serde_json::from_value(serde_json::json!({
+            "version": 2,
+            "number": 5,
+            "hash": "",
+            "previous": "d".repeat(64),
+            "validator": peer(2),
+            "relay": peer(3),
+            "merkel": "e".repeat(64),
+            "body_hash": "f".repeat(64),
+            "signature": sign(),
+            "date": "2024-01-02 03:04:05 UTC",
+        }))
+        .unwrap();
+        check(
+            &header,
+            "header",
+            "000000066865616465720000000200000000000000050000004064646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b39400000026002408011220ed4928c628d1c2c6eae90338905995612959273a5c63f93636c14614ac8737d10000004065656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565656565000000406666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666666600000017323032342d30312d30322030333a30343a303520555443",
+            "bc2c108bc73d69fc336a9f07c03635561890a1e11699877bd6b7a2a258261105",
+        );
+    }
+
+    #[test]
+    fn merkle_vector() {
+        let (a, b, c) = ("a".repeat(64), "b".repeat(64), "c".repeat(64));
+        let tree = MerkleTree::new(vec![&a, &b, &c]);
+        let leaves = [
+            "88df0645999a1bc9dec19086e862403750a069436d7ecf7775256f78279b3fcb",
+            "a2665a1b9f0ab724574a558b6fbd6b6ea9e74f0e1766fdf9cd8842659a8dff06",
+            "ae768c16228c1a2066e9502da4b5fe18beee6abdefb80f620e40c7c1bd9392fb",
+        ];
+        let node = "0213bc5332c31aab3d5a53644449e5b54d6f74acfb722a8f9eaea7c248cb8d95";
+        let root = "5cc9a9352350c5a30daf9d4472af4e25dbd38ec249c9ca79dc27689580a43ee6";
+        assert_eq!(tree.root(), root);
+
+        //siblings in the proofs are the leaves and the node of the docs
+        let proof = tree.proof(&a, 0).unwrap();
+        assert_eq!(proof.path[0].hash, leaves[1]);
+        assert_eq!(proof.path[1].hash, leaves[2]);
+        assert_eq!(tree.proof(&b, 1).unwrap().path[0].hash, leaves[0]);
+
+        let proof = tree.proof(&c, 2).unwrap();
+        assert_eq!(proof.path.len(), 1);
+        assert_eq!(proof.path[0].hash, node);
+        assert!(proof.path[0].left);
+        assert!(proof.verify(root));
+    }
+
+    #[test]
+    fn message_vectors() {
+        let vote = Vote {
+            voter: peer(2),
+            candidate: peer(3),
+            round: 5,
+            signature: sign(),
+        };
+        check(&vote, "vote", "00000004766f7465000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b39400000026002408011220ed4928c628d1c2c6eae90338905995612959273a5c63f93636c14614ac8737d10000000000000005", "d5595ca8ecf81e0c18ebfd4e35fe895da0a6f1b38261824b3bd4e8a6f6ce2433");
+
+        let attestation = Attestation {
+            validator: peer(2),
+            number: 5,
+            hash: "d".repeat(64),
+            signature: sign(),
+        };
+        check(&attestation, "attestation", "0000000b6174746573746174696f6e000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b39400000000000000050000004064646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464", "2656fbfc46a4224c734e5b5de47bd0d3b963417afc1519a29d415052254a64fd");
+
+        let vsync: VSync = serde_json::from_value(serde_json::json!({
+            "relay": peer(3),
+            "peerid": peer(2),
+            "msg": "I'm Synced",
+            "number": 5,
+            "tip": "d".repeat(64),
+            "signature": sign(),
+        }))
+        .unwrap();
+        check(&vsync, "vsync", "000000057673796e6300000026002408011220ed4928c628d1c2c6eae90338905995612959273a5c63f93636c14614ac8737d1000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b3940000000a49276d2053796e63656400000000000000050000004064646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464646464", "bded1f768c686e7605ffb77ef7935c18fc2e38672c9f7905fa47be9b4db53421");
+
+        let membership = Membership {
+            peerid: peer(2),
+            action: Action::Pause,
+            round: 5,
+            signature: sign(),
+        };
+        check(&membership, "membership", "0000000a6d656d62657273686970000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b394010000000000000005", "b61e87a3a154009000c7bb62aed403a91d1d78ede04cb2790fed7fbb7b249a95");
+
+        let outnode = Outnode {
+            outnode: peer(2),
+            relay: peer(3),
+            round: 5,
+            signature: Vec::new(),
+        };
+        check(&outnode, "outnode", "000000076f75746e6f6465000000260024080112208139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b39400000026002408011220ed4928c628d1c2c6eae90338905995612959273a5c63f93636c14614ac8737d10000000000000005", "66df5b6387cbf89e48d4fc945e0db89773f354b40abea92d9b25d1f2b813eee3");
+    }
+}
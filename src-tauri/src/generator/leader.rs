@@ -2,28 +2,29 @@ use std::collections::HashMap;
 
 use chrono::{DateTime, Duration, Utc};
 use libp2p::{gossipsub::IdentTopic, PeerId, Swarm};
-use mongodb::{
-    bson::{doc, from_document, Document},
-    Collection, Database,
-};
+use sp_core::ed25519::Public;
 use tauri::Emitter;
 
 use crate::{
     events::gossip_messages::handler::GossipMessages,
     tools::{
+        settings::Settings,
+        storage::Storage,
         trun_sync::{Sync, Turn},
         wrongdoer::WrongDoer,
     },
 };
 
-use super::{swarm::CentichainBehaviour, validator::Validator};
+use super::{block::message::BlockMessage, swarm::CentichainBehaviour, vote::Vote};
 
 pub struct Leader {
     pub peerid: Option<PeerId>,
     pub timer: LeaderTime,
     pub time: Option<DateTime<Utc>>,
+    timeout: Duration,
     pub in_check: bool,
-    pub votes: Vec<PeerId>,
+    pub round: u64,
+    pub votes: HashMap<PeerId, PeerId>, //one vote (candidate) per voter in the round
 }
 
 #[derive(Debug, PartialEq)]
@@ -52,8 +53,10 @@ impl Leader {
             peerid,
             timer: LeaderTime::Off,
             time: None,
+            timeout: Duration::seconds(Settings::load().leader_timeout as i64),
             in_check: false,
-            votes: Vec::new(),
+            round: 0,
+            votes: HashMap::new(),
         }
     }
 
@@ -64,9 +67,35 @@ impl Leader {
 
     // Start leader time for checking its block
     pub fn timer_start(&mut self) {
+        self.timer_start_from(Utc::now());
+    }
+
+    // Start leader time from the time that its block was expected
+    pub fn timer_start_from(&mut self, expected: DateTime<Utc>) {
         self.timer.start();
-        // Set time to 59 seconds from now
-        self.time.get_or_insert(Utc::now() + Duration::seconds(59));
+        // Set time to the leader timeout from the expected time
+        self.time.get_or_insert(expected + self.timeout);
+    }
+
+    // Check deadline of the leader and if it passed start voting for a new leader
+    // it is called by the timer of the event loop too so a silent leader is replaced even without new transactions
+    pub async fn check_timeout<'a, S: Storage>(
+        &mut self,
+        db: &'a S,
+        swarm: &mut Swarm<CentichainBehaviour>,
+        peerid: &PeerId,
+        wallet: &Public,
+        private: &String,
+        window: &tauri::Window,
+        turn: &mut Turn,
+        sync_state: &mut Sync,
+    ) -> Result<(), &'a str> {
+        if self.timer == LeaderTime::On && !self.in_check && Utc::now() > self.time.unwrap() {
+            self.start_voting(db, swarm, peerid, wallet, private, window, turn, sync_state)
+                .await
+        } else {
+            Ok(())
+        }
     }
 
     // Update to new leader
@@ -75,6 +104,7 @@ impl Leader {
         self.time = None;
         self.peerid = peerid;
         self.in_check = false;
+        self.votes.clear();
         // Emit leader event if peerid is present
         if peerid.is_some() {
             window.emit("leader", peerid.unwrap().to_string()).unwrap();
@@ -82,11 +112,13 @@ impl Leader {
     }
 
     // Propagate validator's vote about new leader to the network
-    pub async fn start_voting<'a>(
+    pub async fn start_voting<'a, S: Storage>(
         &mut self,
-        db: &'a Database,
+        db: &'a S,
         swarm: &mut Swarm<CentichainBehaviour>,
         peerid: &PeerId,
+        wallet: &Public,
+        private: &String,
         window: &tauri::Window,
         turn: &mut Turn,
         sync_state: &mut Sync,
@@ -94,8 +126,38 @@ impl Leader {
         // Set in_check of leader to true
         self.check_start();
 
+        // Round of voting is height of the last block and votes of former rounds are dropped
+        match db.last_block().await {
+            Ok(last) => {
+                let round = last.map(|block| block.header.number).unwrap_or(0);
+                if round != self.round {
+                    self.round = round;
+                    self.votes.clear();
+                }
+            }
+            Err(e) => return Err(e),
+        }
+
+        // A paused leader keeps its registration, so its leadership is only handed over
+        let left = self.peerid.unwrap();
+        match db.find_validator(&left).await {
+            Ok(Some(validator)) if validator.paused => {
+                window
+                    .emit(
+                        "status",
+                        format!("Leadership of paused leader handed over: {}", left),
+                    )
+                    .unwrap();
+                return self
+                    .find_and_post_new_leader(db, swarm, peerid, wallet, private, window, turn)
+                    .await;
+            }
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
         // First, delete left leader from validators as a wrongdoer
-        match WrongDoer::remove(db, self.peerid.unwrap().clone(), turn, sync_state, window).await {
+        match WrongDoer::remove(db, left, turn, sync_state, window).await {
             Ok(wrongdoer) => {
                 window
                     .emit(
@@ -105,7 +167,7 @@ impl Leader {
                     .unwrap();
 
                 // Propagate vote to the network
-                self.find_and_post_new_leader(db, swarm, peerid, window, turn)
+                self.find_and_post_new_leader(db, swarm, peerid, wallet, private, window, turn)
                     .await
             }
             Err(e) => Err(e),
@@ -113,38 +175,51 @@ impl Leader {
     }
 
     // Finding new leader for sending it as vote
-    async fn find_and_post_new_leader<'a>(
+    async fn find_and_post_new_leader<'a, S: Storage>(
         &mut self,
-        db: &'a Database,
+        db: &'a S,
         swarm: &mut Swarm<CentichainBehaviour>,
         peerid: &PeerId,
+        wallet: &Public,
+        private: &String,
         window: &tauri::Window,
         turn: &mut Turn,
     ) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        // Finding validator that its waiting is 0
-        let filter = doc! {"waiting": 0};
-        let query = collection.find_one(filter).await;
+        // Elect the validator like the block elects its next leader, so every validator votes for the same one
+        let last_block = match db.last_block().await {
+            Ok(last_block) => last_block,
+            Err(e) => return Err(e),
+        };
+        let query = db.validators().await;
         match query {
-            Ok(opt) => match opt {
-                // If there was a validator that its waiting is 0, post it as vote
-                Some(doc) => {
-                    let validator: Validator = from_document(doc).unwrap();
-                    let vote = GossipMessages::LeaderVote(validator.peerid);
+            Ok(validators) => match last_block.and_then(|b| BlockMessage::elect(&b, &validators)) {
+                // If a validator was elected, post it as signed vote
+                Some(candidate) => {
+                    let vote = match Vote::new(*peerid, candidate, self.round, wallet, private) {
+                        Ok(vote) => vote,
+                        Err(e) => return Err(e),
+                    };
+                    // Own vote is counted too because gossipsub doesn't return own messages
+                    self.votes.insert(vote.voter, vote.candidate);
+                    let vote = GossipMessages::LeaderVote(vote);
                     let str_vote = serde_json::to_string(&vote).unwrap();
                     match swarm
                         .behaviour_mut()
                         .gossipsub
                         .publish(IdentTopic::new("validator"), str_vote)
                     {
-                        Ok(_) => Ok(window.emit("leader", validator.peerid.to_string()).unwrap()),
-                        Err(_) => Err("Error while gossiping message-(generator/leader 120)"),
+                        Ok(_) => Ok(window.emit("leader", candidate.to_string()).unwrap()),
+                        Err(_) => Err("Error while gossiping message-(generator/leader 212)"),
                     }
                 }
 
-                // If there isn't validator that its waiting was 0, post current validator own peerid as vote
+                // If no validator is in turn, post current validator own peerid as vote
                 None => {
-                    let vote = GossipMessages::LeaderVote(peerid.clone());
+                    let vote = match Vote::new(*peerid, *peerid, self.round, wallet, private) {
+                        Ok(vote) => vote,
+                        Err(e) => return Err(e),
+                    };
+                    let vote = GossipMessages::LeaderVote(vote);
                     let str_vote = serde_json::to_string(&vote).unwrap();
                     match swarm
                         .behaviour_mut()
@@ -156,41 +231,77 @@ impl Leader {
                             turn.on(window);
                             Ok(window.emit("leader", peerid.to_string()).unwrap())
                         }
-                        Err(_) => Err("Error while gossiping message-(generator/leader 137)"),
+                        Err(_) => Err("Error while gossiping message-(generator/leader 234)"),
                     }
                 }
             },
             Err(_) => {
-                Err("Error during find validator that its waiting is 0-(generator/leader 142)")
+                Err("Error during find validator that its waiting is 0-(generator/leader 239)")
             }
         }
     }
 
     // Check votes and if it was quorum set it as leader
-    pub async fn check_votes<'a>(
+    // only signed votes of registered validators are counted and each validator has one vote in a round
+    pub async fn check_votes<'a, S: Storage>(
         &mut self,
-        db: &Database,
-        vote: PeerId,
+        db: &S,
+        vote: Vote,
         peerid: &PeerId,
         turn: &mut Turn,
         window: &tauri::Window,
     ) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        // Get count documents for knowing votes are upper than 50% of documents number or not
-        match collection.count_documents(doc! {}).await {
+        // Voter must be a validator and the vote must be signed by its wallet
+        // (it is checked before the round, so an unsigned vote can't move the round)
+        match db.find_validator(&vote.voter).await {
+            Ok(Some(validator)) => {
+                if validator.wallet != vote.signature.key || !vote.verify() {
+                    return Ok(window
+                        .emit("status", format!("Incorrect vote from: {}", vote.voter))
+                        .unwrap());
+                }
+            }
+            Ok(None) => return Ok(()),
+            Err(_) => return Err("Error while finding the voter-(generator/leader 265)"),
+        }
+
+        // Round can't be ahead of the local tip
+        match db.last_block().await {
+            Ok(last) => {
+                if vote.round > last.map(|block| block.header.number).unwrap_or(0) {
+                    return Ok(());
+                }
+            }
+            Err(_) => return Err("Error while finding the last block-(generator/leader 275)"),
+        }
+
+        // Votes of former rounds are ignored and a newer round resets votes
+        if vote.round < self.round {
+            return Ok(());
+        }
+        if vote.round > self.round {
+            self.round = vote.round;
+            self.votes.clear();
+        }
+        if self.votes.contains_key(&vote.voter) {
+            return Ok(());
+        }
+
+        // Get count of validators for knowing votes are upper than 50% of validators number or not
+        match db.validators_count().await {
             Ok(count) => {
-                self.votes.push(vote);
+                self.votes.insert(vote.voter, vote.candidate);
 
                 // If votes are upper than 50% of validators then find most vote to set as leader
                 if self.votes.len() >= ((count / 2) + 1) as usize {
                     let mut hashmap_of_votes = HashMap::new(); // Make hashmap to group by vote per peerid
-                    for v in self.votes.clone() {
-                        *hashmap_of_votes.entry(v).or_insert(0) += 1; // Plus 1 if key is repetitive
+                    for v in self.votes.values() {
+                        *hashmap_of_votes.entry(*v).or_insert(0) += 1; // Plus 1 if key is repetitive
                     }
-                    // Get the most vote and change leader
+                    // Get the most vote and change leader (equal votes are decided by peerid for all validators)
                     let result = hashmap_of_votes
                         .iter()
-                        .max_by_key(|v| *v)
+                        .max_by_key(|(candidate, count)| (**count, candidate.to_string()))
                         .unwrap()
                         .0
                         .clone();
@@ -204,7 +315,7 @@ impl Leader {
                 }
                 Ok(())
             }
-            Err(_) => Err("Error while get count of validators' doc-(generator/leader 184)"),
+            Err(_) => Err("Error while get count of validators' doc-(generator/leader 318)"),
         }
     }
 }
@@ -0,0 +1,277 @@
+use std::sync::Mutex;
+
+use centichain_keypair::CentichainKey;
+use libp2p::{gossipsub::IdentTopic, PeerId};
+use serde::{Deserialize, Serialize};
+use sp_core::{
+    ed25519::{Public, Signature},
+    Pair,
+};
+use tauri::Emitter;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    events::{gossip_messages::handler::GossipMessages, node::Node},
+    tools::{changeset::ChangeSet, storage::Storage, wrongdoer::WrongDoer},
+};
+
+use super::{
+    block::{block::Block, forks::MAX_REORG_DEPTH, header::Sign},
+    encoding::Canonical,
+};
+
+//sender of requests from tauri commands to the running event loop
+static REQUESTS: Mutex<Option<UnboundedSender<Action>>> = Mutex::new(None);
+
+// Change of a validator's membership that the validator announces itself
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Action {
+    //validator is shutting down cleanly and leaves validators
+    Exit,
+    //validator keeps its registration but is not elected as leader
+    Pause,
+    Resume,
+}
+
+// Signed membership message of a validator
+// round is the height of the last block of the validator, so old messages can't be replayed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Membership {
+    pub peerid: PeerId,
+    pub action: Action,
+    pub round: u64,
+    pub signature: Sign,
+}
+
+impl Membership {
+    pub fn new<'a>(
+        peerid: PeerId,
+        action: Action,
+        round: u64,
+        wallet: &Public,
+        private: &String,
+    ) -> Result<Self, &'a str> {
+        let mut membership = Self {
+            peerid,
+            action,
+            round,
+            signature: Sign {
+                signatgure: Signature::from_raw([0; 64]),
+                key: *wallet,
+            },
+        };
+        match CentichainKey::signing(private, &membership.hash()) {
+            Ok(signed) => {
+                membership.signature.signatgure = signed;
+                Ok(membership)
+            }
+            Err(_) => Err("Error during signing the membership message-(generator/membership 68)"),
+        }
+    }
+
+    fn hash(&self) -> String {
+        self.canonical_hash("membership")
+    }
+
+    // Send a request of the operator to the event loop (false if the node is not running)
+    pub fn request(action: Action) -> bool {
+        match REQUESTS.lock().unwrap().as_ref() {
+            Some(sender) => sender.send(action).is_ok(),
+            None => false,
+        }
+    }
+
+    // Receiver of operator requests for the event loop (a new event loop replaces the former receiver)
+    pub fn requests() -> UnboundedReceiver<Action> {
+        let (sender, receiver) = unbounded_channel();
+        *REQUESTS.lock().unwrap() = Some(sender);
+        receiver
+    }
+
+    // Make, gossip and apply the membership message of this validator
+    pub async fn announce<'n, S: Storage>(
+        action: Action,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let window = node.window;
+        let peerid = node.peerid;
+        let round = node
+            .last_block
+            .first()
+            .map(|b| b.header.number)
+            .unwrap_or(0);
+        let membership = match Self::new(*peerid, action, round, node.wallet, node.private) {
+            Ok(membership) => membership,
+            Err(e) => return Err(e),
+        };
+
+        let message = GossipMessages::Membership(membership.clone());
+        let str_message = serde_json::to_string(&message).unwrap();
+        if node
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(IdentTopic::new("validator"), str_message)
+            .is_err()
+        {
+            window
+                .emit("status", "Membership message could not be gossiped.")
+                .unwrap();
+        }
+
+        //gossipsub doesn't return own messages, so it is applied here too
+        membership.handle(Some(*peerid), node).await
+    }
+
+    // Check the message of a registered validator
+    // author is the signed source of the gossip message and the wallet must be the wallet of the validator in validators
+    async fn verify<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        author: Option<PeerId>,
+        last_block: &[Block],
+    ) -> Result<Option<&'a str>, &'a str> {
+        if author != Some(self.peerid) {
+            return Ok(Some("Membership message is not from its validator"));
+        }
+        match db.find_validator(&self.peerid).await {
+            Ok(Some(validator)) => {
+                if validator.wallet != self.signature.key
+                    || !sp_core::ed25519::Pair::verify(
+                        &self.signature.signatgure,
+                        self.hash(),
+                        &self.signature.key,
+                    )
+                {
+                    return Ok(Some("Membership message has an incorrect signature"));
+                }
+            }
+            Ok(None) => return Ok(Some("Membership message is not from a validator")),
+            Err(e) => return Err(e),
+        }
+        match last_block.first() {
+            Some(last)
+                if self.round <= last.header.number
+                    && self.round.saturating_add(MAX_REORG_DEPTH) > last.header.number =>
+            {
+                Ok(None)
+            }
+            _ => Ok(Some("Membership message is not on a recent block")),
+        }
+    }
+
+    // Handle membership message of a validator
+    pub async fn handle<'n, S: Storage>(
+        &self,
+        author: Option<PeerId>,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let db = node.db;
+        let window = node.window;
+        let (peerid, wallet, private) = (node.peerid, node.wallet, node.private);
+        match self.verify(db, author, node.last_block).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => {
+                return Ok(window
+                    .emit("status", format!("{}: {}", reason, self.peerid))
+                    .unwrap())
+            }
+            Err(e) => return Err(e),
+        }
+
+        match self.action {
+            Action::Exit => {
+                if node.leader.peerid == Some(self.peerid) && &self.peerid != peerid {
+                    // Start voting for a new leader if the leader left (voting removes it)
+                    node.leader
+                        .start_voting(
+                            db,
+                            node.swarm,
+                            peerid,
+                            wallet,
+                            private,
+                            window,
+                            node.turn,
+                            node.sync_state,
+                        )
+                        .await
+                } else {
+                    match WrongDoer::remove(db, self.peerid, node.turn, node.sync_state, window)
+                        .await
+                    {
+                        Ok(exited) => Ok(window
+                            .emit("status", format!("Validator exited: {}", exited))
+                            .unwrap()),
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+            Action::Pause | Action::Resume => {
+                let paused = self.action == Action::Pause;
+                match db.find_validator(&self.peerid).await {
+                    Ok(Some(previous)) if previous.paused != paused => {
+                        let mut validator = previous.clone();
+                        validator.paused = paused;
+                        let mut changes = ChangeSet::new();
+                        changes.update_validator(&previous, validator);
+                        match db.commit(None, &changes).await {
+                            Ok(_) => {}
+                            Err(e) => return Err(e),
+                        }
+                        window
+                            .emit(
+                                "status",
+                                format!("Validator {:?}d: {}", self.action, self.peerid),
+                            )
+                            .unwrap();
+
+                        // A leader that pauses hands over its leadership
+                        if paused && node.leader.peerid == Some(self.peerid) {
+                            if &self.peerid == peerid {
+                                node.turn.off(0, window);
+                                Ok(())
+                            } else {
+                                node.leader
+                                    .start_voting(
+                                        db,
+                                        node.swarm,
+                                        peerid,
+                                        wallet,
+                                        private,
+                                        window,
+                                        node.turn,
+                                        node.sync_state,
+                                    )
+                                    .await
+                            }
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+}
+
+impl Canonical for Action {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let action: u8 = match self {
+            Self::Exit => 0,
+            Self::Pause => 1,
+            Self::Resume => 2,
+        };
+        action.encode(out);
+    }
+}
+
+// Fields of the membership message that its signature commits to
+impl Canonical for Membership {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.peerid.encode(out);
+        self.action.encode(out);
+        self.round.encode(out);
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use tauri::Emitter;
+
+use crate::tools::{changeset::ChangeSet, settings::Settings, storage::Storage};
+
+use super::transaction::Transaction;
+
+// Mempool of validated transactions that wait for a block
+// spends of its transactions are not written into the storage, they are validated again after every accepted block
+#[derive(Debug)]
+pub struct Mempool {
+    entries: Vec<Entry>,
+    max_size: usize,
+    ttl: Duration,
+}
+
+#[derive(Debug)]
+struct Entry {
+    transaction: Transaction,
+    fee_rate: Decimal,
+    added: DateTime<Utc>,
+}
+
+impl Entry {
+    fn new(transaction: Transaction) -> Self {
+        //fee rate is fee per byte of the serialized transaction
+        let size = serde_json::to_string(&transaction).unwrap().len();
+        Self {
+            fee_rate: transaction.fee / Decimal::from(size),
+            transaction,
+            added: Utc::now(),
+        }
+    }
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        let settings = Settings::load();
+        Self {
+            entries: Vec::new(),
+            max_size: settings.mempool_max_size,
+            ttl: Duration::seconds(settings.mempool_ttl as i64),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.entries.iter().any(|e| e.transaction.hash == hash)
+    }
+
+    // Time of the transaction that waits longer than others
+    pub fn oldest(&self) -> Option<DateTime<Utc>> {
+        self.entries.iter().map(|e| e.added).min()
+    }
+
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.entries.iter().map(|e| e.transaction.clone()).collect()
+    }
+
+    // Insert a transaction (a full mempool evicts its lowest fee rate transaction for a higher one)
+    pub fn insert<'a>(&mut self, transaction: Transaction) -> Result<(), &'a str> {
+        if self.contains(&transaction.hash) {
+            return Err("Transaction is already in mempool.");
+        }
+
+        let entry = Entry::new(transaction);
+        if self.entries.len() >= self.max_size {
+            let lowest = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, e)| e.fee_rate)
+                .map(|(i, e)| (i, e.fee_rate));
+            match lowest {
+                Some((i, fee_rate)) if fee_rate < entry.fee_rate => {
+                    self.entries.remove(i);
+                }
+                _ => return Err("Mempool is full and fee of transaction is too low."),
+            }
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<Transaction> {
+        match self.entries.iter().position(|e| e.transaction.hash == hash) {
+            Some(i) => Some(self.entries.remove(i).transaction),
+            None => None,
+        }
+    }
+
+    // Take transactions with the highest fee rates for a new block
+    pub fn template(&mut self, max: usize) -> Vec<Transaction> {
+        self.entries.sort_by(|a, b| b.fee_rate.cmp(&a.fee_rate));
+        let count = max.min(self.entries.len());
+        self.entries.drain(..count).map(|e| e.transaction).collect()
+    }
+
+    // Remove transactions that stayed longer than time-to-live and return count of them
+    pub fn expire(&mut self) -> usize {
+        let now = Utc::now();
+        let before = self.entries.len();
+        self.entries.retain(|e| now - e.added < self.ttl);
+        before - self.entries.len()
+    }
+
+    // Validate remaining transactions with the storage after an accepted block
+    // transactions that their inputs are spent (or are expired) will be removed
+    pub async fn revalidate<S: Storage>(&mut self, db: &S, window: &tauri::Window) {
+        self.expire();
+        let mut changes = ChangeSet::new();
+        let mut valid = Vec::new();
+        for entry in self.entries.drain(..) {
+            if entry.transaction.validate(db, &mut changes).await.is_ok() {
+                valid.push(entry);
+            }
+        }
+        self.entries = valid;
+        window.emit("mempool", self.transactions()).unwrap();
+    }
+}
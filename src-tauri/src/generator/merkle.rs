@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Merkle tree of the hashes of a block (coinbase hash first and then hashes of transactions)
+// leaf = sha256(0x00 ‖ hash), node = sha256(0x01 ‖ left ‖ right) where hashes are their hex strings,
+// a node without a pair at the end of a level goes up unchanged (nothing is duplicated)
+// so every tree has one root and every leaf has one proof
+#[derive(Debug)]
+pub struct MerkleTree {
+    levels: Vec<Vec<String>>,
+}
+
+// Sibling of a node in the path from a leaf to the root
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Step {
+    pub hash: String,
+    pub left: bool,
+}
+
+// Inclusion proof of a hash in a merkle root
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Proof {
+    pub leaf: String,
+    pub index: u64,
+    pub path: Vec<Step>,
+}
+
+impl MerkleTree {
+    pub fn new(hashes: Vec<&String>) -> Self {
+        let mut levels = vec![hashes
+            .into_iter()
+            .map(|h| Self::leaf(h))
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => Self::node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    fn leaf(hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update([0u8]);
+        hasher.update(hash);
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn node(left: &str, right: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update([1u8]);
+        hasher.update(left);
+        hasher.update(right);
+        format!("{:x}", hasher.finalize())
+    }
+
+    // Root of the tree (a tree without any hashes has an empty root)
+    pub fn root(&self) -> String {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Proof of the hash of the index
+    pub fn proof(&self, leaf: &String, index: usize) -> Option<Proof> {
+        if index >= self.levels[0].len() || self.levels[0][index] != Self::leaf(leaf) {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                path.push(Step {
+                    hash: level[sibling].clone(),
+                    left: sibling < position,
+                });
+            }
+            position /= 2;
+        }
+        Some(Proof {
+            leaf: leaf.clone(),
+            index: index as u64,
+            path,
+        })
+    }
+}
+
+impl Proof {
+    // Check that the leaf is in the tree of the root
+    pub fn verify(&self, root: &str) -> bool {
+        let mut hash = MerkleTree::leaf(&self.leaf);
+        for step in &self.path {
+            hash = if step.left {
+                MerkleTree::node(&step.hash, &hash)
+            } else {
+                MerkleTree::node(&hash, &step.hash)
+            };
+        }
+        hash == root
+    }
+}
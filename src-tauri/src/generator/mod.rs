@@ -1,16 +1,23 @@
 use sha2::{Digest, Sha256};
 
 pub mod block;
+pub mod encoding;
 pub mod leader;
+pub mod membership;
+pub mod mempool;
+pub mod merkle;
+pub mod outnode;
 pub mod relay;
 pub mod swarm;
 pub mod transaction;
 pub mod validator;
+pub mod vote;
 
 pub struct MerkelRoot;
 
 impl MerkelRoot {
     //make a merkel root from hashs of transactions
+    //it is kept for hashes of transactions and coinbase and for headers before version 3 (see generator/merkle)
     pub fn make(transactions: Vec<&String>) -> Vec<String> {
         let mut hashs: Vec<String> = Vec::new();
         for trx in transactions {
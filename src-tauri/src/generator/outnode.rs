@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use libp2p::{identity::PublicKey, PeerId};
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::{
+    events::node::Node,
+    tools::{settings::Settings, storage::Storage, wrongdoer::WrongDoer},
+};
+
+use super::{block::forks::MAX_REORG_DEPTH, encoding::Canonical, relay::Relay};
+
+// Notice of a relay that a validator connected to it was disconnected
+// the relay signs the canonical hash of outnode, relay and round (height of its last block) by its libp2p key,
+// so the notice is checked by the public key in the relay's peer id
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Outnode {
+    pub outnode: PeerId,
+    pub relay: PeerId,
+    pub round: u64,
+    pub signature: Vec<u8>,
+}
+
+impl Outnode {
+    fn hash(&self) -> String {
+        self.canonical_hash("outnode")
+    }
+
+    // Check signature of the notice by the key of the relay
+    pub fn verify(&self) -> bool {
+        let multihash = self.relay.as_ref();
+        //only peer ids of ed25519 keys include their public key
+        if multihash.code() != 0 {
+            return false;
+        }
+        match PublicKey::try_decode_protobuf(multihash.digest()) {
+            Ok(key) => key.verify(self.hash().as_bytes(), &self.signature),
+            Err(_) => false,
+        }
+    }
+
+    // Relays that can report the outnode: the relay that the outnode is registered with and the relay of this node
+    // (no relay can report a node that is not a validator)
+    async fn known_relays<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        relay: &Relay,
+    ) -> Result<Vec<PeerId>, &'a str> {
+        match db.find_validator(&self.outnode).await {
+            Ok(Some(validator)) => {
+                let mut relays = vec![validator.relay];
+                if let Some(own) = relay.peerid {
+                    if own != validator.relay {
+                        relays.push(own);
+                    }
+                }
+                Ok(relays)
+            }
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Fields of the notice that the relay signs
+impl Canonical for Outnode {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.outnode.encode(out);
+        self.relay.encode(out);
+        self.round.encode(out);
+    }
+}
+
+// Outnode notices that wait for confirmation of more relays
+// reporters of an outnode are kept with the round of their notice
+#[derive(Debug)]
+pub struct OutnodeReports {
+    reports: HashMap<PeerId, HashMap<PeerId, u64>>,
+    confirmations: usize,
+}
+
+impl OutnodeReports {
+    pub fn new() -> Self {
+        Self {
+            reports: HashMap::new(),
+            confirmations: Settings::load().outnode_confirmations,
+        }
+    }
+
+    // Remove notices that are too old to be confirmed (like old notices that can't be replayed)
+    pub fn prune(&mut self, tip: u64) {
+        for reporters in self.reports.values_mut() {
+            reporters.retain(|_, round| round.saturating_add(MAX_REORG_DEPTH) > tip);
+        }
+        self.reports.retain(|_, reporters| !reporters.is_empty());
+    }
+
+    // Handle outnode notice of a relay
+    // the outnode is removed (or a new leader is voted if it was the leader) when enough known relays reported it
+    pub async fn handle<'n, S: Storage>(
+        notice: Outnode,
+        node: &mut Node<'n, S>,
+    ) -> Result<(), &'n str> {
+        let db = node.db;
+        let window = node.window;
+        if !notice.verify() {
+            return Ok(window
+                .emit(
+                    "status",
+                    format!(
+                        "Outnode notice has an incorrect signature: {}",
+                        notice.relay
+                    ),
+                )
+                .unwrap());
+        }
+        let known_relays = match notice.known_relays(db, node.relay).await {
+            Ok(known_relays) => known_relays,
+            Err(e) => return Err(e),
+        };
+        if !known_relays.contains(&notice.relay) {
+            return Ok(window
+                .emit(
+                    "status",
+                    format!("Outnode notice is not from a known relay: {}", notice.relay),
+                )
+                .unwrap());
+        }
+        //old notices can't be replayed
+        match node.last_block.first() {
+            Some(last) if notice.round.abs_diff(last.header.number) < MAX_REORG_DEPTH => {
+                node.outnodes.prune(last.header.number)
+            }
+            _ => return Ok(()),
+        }
+
+        let reporters = node.outnodes.reports.entry(notice.outnode).or_default();
+        reporters.insert(notice.relay, notice.round);
+        //when the outnode is registered with the relay of this node, that relay is the only one that can report it
+        //and 0 confirmations means the signed notice of a known relay is enough
+        if reporters.len() < node.outnodes.confirmations.min(known_relays.len()) {
+            return Ok(());
+        }
+        node.outnodes.reports.remove(&notice.outnode);
+
+        if node.leader.peerid.is_some() && notice.outnode == node.leader.peerid.unwrap() {
+            // Start voting for a new leader if the current leader is the outnode
+            node.leader
+                .start_voting(
+                    db,
+                    node.swarm,
+                    node.peerid,
+                    node.wallet,
+                    node.private,
+                    window,
+                    node.turn,
+                    node.sync_state,
+                )
+                .await
+        } else {
+            // Remove the outnode from the validators list
+            match WrongDoer::remove(db, notice.outnode, node.turn, node.sync_state, window).await {
+                Ok(outnode) => Ok(window
+                    .emit("status", format!("{} is outnode", outnode.to_string()))
+                    .unwrap()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+}
@@ -8,8 +8,9 @@ use libp2p::{
     swarm::NetworkBehaviour,
     PeerId, StreamProtocol, Swarm, SwarmBuilder,
 };
-use mongodb::Database;
 use serde::{Deserialize, Serialize};
+
+use crate::tools::storage::Storage;
 mod addresses;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,9 +31,9 @@ pub struct CentichainBehaviour {
 
 pub trait Features {
     async fn new() -> (Pin<Box<Swarm<CentichainBehaviour>>>, PeerId); //config new swarm
-    async fn dial<'a>(
+    async fn dial<'a, S: Storage>(
         swarm: &mut Swarm<CentichainBehaviour>,
-        db: &Database,
+        db: &S,
         window: &tauri::Window,
     ) -> Result<(), &'a str>; //dialing with a stable relay to comunicate with the network
 }
@@ -94,9 +95,9 @@ impl Features for CentichainBehaviour {
     }
 
     //dialing to realys as random
-    async fn dial<'a>(
+    async fn dial<'a, S: Storage>(
         swarm: &mut Swarm<CentichainBehaviour>,
-        db: &Database,
+        db: &S,
         window: &tauri::Window,
     ) -> Result<(), &'a str> {
         match Addresses::contact(swarm, db, window).await {
@@ -1,14 +1,23 @@
+use std::collections::HashMap;
+
 use chrono::{SubsecRound, Utc};
-use mongodb::Database;
 use reqwest::Client;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use sp_core::{crypto::Ss58Codec, ed25519::Public, Pair};
 
-use crate::tools::{for_front::make_trx::ResBody, utxo::UTXO};
+use crate::tools::{
+    changeset::ChangeSet, for_front::make_trx::ResBody, invalid::Invalid, storage::Storage,
+    utxo::UTXO,
+};
+
+use super::{block::header::Sign, encoding::Canonical, HashMaker, MerkelRoot};
 
-use super::{block::header::Sign, HashMaker, MerkelRoot};
+// Version of the hashing that new transactions have
+// 0: hashes of inputs, outputs and unspents are made from their json
+// 1: hashes are made from their canonical encoding (see generator/encoding)
+pub const TRANSACTION_VERSION: u32 = 1;
 
 // Define a transaction in the Centichain network
 // The hash of the transaction is derived from the hashes of its inputs and outputs
@@ -25,6 +34,13 @@ pub struct Transaction {
     pub script: Script,
     pub signature: Vec<Sign>,
     pub date: String,
+    //version 0 isn't serialized, so json of former transactions doesn't change
+    #[serde(default, skip_serializing_if = "is_legacy")]
+    pub version: u32,
+}
+
+fn is_legacy(version: &u32) -> bool {
+    *version == 0
 }
 
 // Define a script for highlighting the transaction's signature
@@ -69,6 +85,84 @@ pub struct UnspentData {
     pub value: Decimal,
 }
 
+// Define a conflict between transactions that spend the same utxos
+// it is sent to front as conflict event
+#[derive(Debug, Serialize, Clone)]
+pub struct Conflict {
+    pub transaction: String,
+    pub conflicts: Vec<String>,
+    pub unspent_hashes: Vec<String>,
+}
+
+impl Conflict {
+    // Find mempool transactions that spend utxos of the transaction
+    pub fn in_mempool(transaction: &Transaction, mempool: &Vec<Transaction>) -> Option<Self> {
+        let spends = transaction.spends();
+        let mut conflicts = Vec::new();
+        let mut unspent_hashes = Vec::new();
+        for trx in mempool {
+            let common: Vec<&String> = trx
+                .spends()
+                .into_iter()
+                .filter(|hash| spends.contains(hash))
+                .collect();
+            if !common.is_empty() {
+                conflicts.push(trx.hash.clone());
+                unspent_hashes.extend(common.into_iter().cloned());
+            }
+        }
+
+        if conflicts.is_empty() {
+            None
+        } else {
+            Some(Self {
+                transaction: transaction.hash.clone(),
+                conflicts,
+                unspent_hashes,
+            })
+        }
+    }
+
+    // Find the first transaction of the block that spends a utxo which an earlier transaction spent
+    pub fn in_block(transactions: &Vec<Transaction>) -> Option<Self> {
+        let mut spent: HashMap<&String, &String> = HashMap::new();
+        for trx in transactions {
+            for hash in trx.spends() {
+                if let Some(earlier) = spent.insert(hash, &trx.hash) {
+                    return Some(Self {
+                        transaction: trx.hash.clone(),
+                        conflicts: vec![earlier.clone()],
+                        unspent_hashes: vec![hash.clone()],
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+// Define errors of amount validation of a transaction
+#[derive(Debug, PartialEq)]
+pub enum AmountError {
+    NegativeValue,
+    NegativeFee,
+    NegativeOutput,
+    Unbalanced,
+}
+
+impl AmountError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::NegativeValue => "Transaction is incorrect.(negative value!)",
+            Self::NegativeFee => "Transaction is incorrect.(negative fee!)",
+            Self::NegativeOutput => "Transaction is incorrect.(negative output!)",
+            Self::Unbalanced => {
+                "Transaction is incorrect.(inputs are not equal to outputs and fee!)"
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TxRes {
     pub hash: String,
@@ -78,8 +172,7 @@ pub struct TxRes {
 
 impl Input {
     fn new(response: ResBody) -> Self {
-        let hash_data = serde_json::to_string(&response.utxo_data).unwrap();
-        let hash = HashMaker::generate(&hash_data);
+        let hash = response.utxo_data.canonical_hash("input");
         Self {
             hash,
             number: response.utxo_data.len() as u8,
@@ -90,9 +183,8 @@ impl Input {
 
 impl Output {
     pub fn new(unspents: Vec<Unspent>) -> Self {
-        let str_outputs = serde_json::to_string(&unspents).unwrap();
         let output = Self {
-            hash: HashMaker::generate(&str_outputs),
+            hash: unspents.canonical_hash("output"),
             number: unspents.len(),
             unspents,
         };
@@ -110,66 +202,194 @@ impl Unspent {
             value,
         };
 
-        let hash_data = serde_json::to_string(&data).unwrap();
-
         Self {
-            hash: HashMaker::generate(&hash_data),
+            hash: data.canonical_hash("unspent"),
             data,
         }
     }
 }
 
+impl Canonical for Script {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Single => 0u8.encode(out),
+            Self::Multi => 1u8.encode(out),
+        }
+    }
+}
+
+impl Canonical for Input {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hash.encode(out);
+        self.number.encode(out);
+        self.utxos.encode(out);
+    }
+}
+
+impl Canonical for Output {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hash.encode(out);
+        self.number.encode(out);
+        self.unspents.encode(out);
+    }
+}
+
+impl Canonical for Unspent {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hash.encode(out);
+        self.data.encode(out);
+    }
+}
+
+impl Canonical for UnspentData {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.wallet.encode(out);
+        self.salt.encode(out);
+        self.value.encode(out);
+    }
+}
+
+impl Canonical for Transaction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.version.encode(out);
+        self.hash.encode(out);
+        self.input.encode(out);
+        self.output.encode(out);
+        self.value.encode(out);
+        self.fee.encode(out);
+        self.script.encode(out);
+        self.signature.encode(out);
+        self.date.encode(out);
+    }
+}
+
 impl Transaction {
-    pub async fn validate<'a>(&self, db: &Database) -> Result<bool, &'a str> {
-        //make input and output hash to check hash that is correct or not
-        let inputs_str = serde_json::to_string(&self.input.utxos).unwrap();
-        let outputs_str = serde_json::to_string(&self.output.unspents).unwrap();
-        let input_hash = HashMaker::generate(&inputs_str);
-        let output_hash = HashMaker::generate(&outputs_str);
+    //unspent hashes of the utxos that the transaction spends
+    pub fn spends(&self) -> Vec<&String> {
+        self.input
+            .utxos
+            .iter()
+            .map(|utxo| &utxo.unspent_hash)
+            .collect()
+    }
+
+    //check that value, fee and outputs are not negative and sum of inputs is equal to sum of outputs plus fee
+    pub fn check_amounts(&self) -> Result<(), AmountError> {
+        let zero = Decimal::ZERO;
+        if self.value < zero {
+            return Err(AmountError::NegativeValue);
+        }
+        if self.fee < zero {
+            return Err(AmountError::NegativeFee);
+        }
+        if self
+            .output
+            .unspents
+            .iter()
+            .any(|unspent| unspent.data.value < zero)
+        {
+            return Err(AmountError::NegativeOutput);
+        }
+
+        let sum_input: Decimal = self.input.utxos.iter().map(|utxo| utxo.unspent).sum();
+        let sum_output: Decimal = self
+            .output
+            .unspents
+            .iter()
+            .map(|unspent| unspent.data.value)
+            .sum();
+        if sum_input != sum_output + self.fee {
+            Err(AmountError::Unbalanced)
+        } else {
+            Ok(())
+        }
+    }
+
+    //check hashes and signature of the transaction by itself (without stored utxos)
+    pub fn check(&self) -> Result<(), &'static str> {
+        //make input and output hash by version of the transaction to check hash that is correct or not
+        let (input_hash, output_hash) = match self.version {
+            0 => {
+                let inputs_str = serde_json::to_string(&self.input.utxos).unwrap();
+                let outputs_str = serde_json::to_string(&self.output.unspents).unwrap();
+                (
+                    HashMaker::generate(&inputs_str),
+                    HashMaker::generate(&outputs_str),
+                )
+            }
+            1 => {
+                //hashes of unspents are checked too because they become hashes of new utxos
+                if self
+                    .output
+                    .unspents
+                    .iter()
+                    .any(|unspent| unspent.hash != unspent.data.canonical_hash("unspent"))
+                {
+                    return Err("Transaction is incorrect.(unspent hash problem!)");
+                }
+                (
+                    self.input.utxos.canonical_hash("input"),
+                    self.output.unspents.canonical_hash("output"),
+                )
+            }
+            _ => return Err("Transaction is incorrect.(unknown version!)"),
+        };
 
         //check input and output hash that is correct or not
-        if input_hash == self.input.hash && output_hash == self.output.hash {
-            //make tansaction's hash for check that it is correct or not
-            let hashes = vec![&input_hash, &output_hash];
-            let trx_hash = MerkelRoot::make(hashes);
-
-            //check transaction hash
-            if trx_hash[0] == self.hash {
-                //validating signatrue of trx
-                let sign_check = sp_core::ed25519::Pair::verify(
-                    &self.signature[0].signatgure,
-                    &trx_hash[0],
-                    &self.signature[0].key,
-                );
-
-                //if validation done transaction is correct
-                if sign_check {
-                    //validating input utxos
-                    let mut is_err: Option<&str> = None;
-                    for i in 0..self.input.utxos.len() {
-                        match UTXO::check(&self.input.utxos[i], db, &self.signature[0].key).await {
-                            Ok(_) => {}
-                            Err(e) => {
-                                is_err = Some(e);
-                                break;
-                            }
-                        }
-                    }
+        if input_hash != self.input.hash || output_hash != self.output.hash {
+            return Err("Transaction is incorrect.(input/output hash problem!)");
+        }
 
-                    //if inputs utxo doesn't have any problems return true
-                    if is_err.is_none() {
-                        Ok(true)
-                    } else {
-                        Err(is_err.unwrap())
-                    }
-                } else {
-                    Err("Transaction is incorrect.(siganture problem!)")
+        //make tansaction's hash for check that it is correct or not
+        let hashes = vec![&input_hash, &output_hash];
+        let trx_hash = MerkelRoot::make(hashes);
+        if trx_hash[0] != self.hash {
+            return Err("Transaction is incorrect.(transacrtion hash problem!)");
+        }
+
+        //validating signatrue of trx
+        match self.signature.first() {
+            Some(sign)
+                if sp_core::ed25519::Pair::verify(&sign.signatgure, &trx_hash[0], &sign.key) =>
+            {
+                Ok(())
+            }
+            _ => Err("Transaction is incorrect.(siganture problem!)"),
+        }
+    }
+
+    //validate transaction and stage spending of its inputs in the change set
+    pub async fn validate<'a, S: Storage>(
+        &self,
+        db: &S,
+        changes: &mut ChangeSet,
+    ) -> Result<bool, Invalid<'a>> {
+        if let Err(e) = self.check() {
+            return Err(Invalid::Rejected(e));
+        }
+
+        //input values are checked with stored utxos, so amounts of the transaction can be checked by itself
+        if let Err(e) = self.check_amounts() {
+            return Err(Invalid::Rejected(e.message()));
+        }
+
+        //validating input utxos
+        let mut is_err: Option<Invalid> = None;
+        for i in 0..self.input.utxos.len() {
+            match UTXO::check(&self.input.utxos[i], db, &self.signature[0].key, changes).await {
+                Ok(_) => {}
+                Err(e) => {
+                    is_err = Some(e);
+                    break;
                 }
-            } else {
-                Err("Transaction is incorrect.(transacrtion hash problem!)")
             }
+        }
+
+        //if inputs utxo doesn't have any problems return true
+        if is_err.is_none() {
+            Ok(true)
         } else {
-            Err("Transaction is incorrect.(input/output hash problem!)")
+            Err(is_err.unwrap())
         }
     }
 
@@ -196,6 +416,10 @@ impl Transaction {
         let mut unspents = Vec::new();
         let change_wallet: Public = public_key.parse().unwrap();
 
+        if sum_input < value + fee {
+            return Err("Not enough centies for the transaction!");
+        }
+
         match to.parse() {
             Ok(to_wallet) => {
                 //calculat change
@@ -227,6 +451,7 @@ impl Transaction {
                     script: Script::Single,
                     signature: vec![signature],
                     date: Utc::now().round_subsecs(0).to_string(),
+                    version: TRANSACTION_VERSION,
                 };
 
                 let url = format!("http://{}:33369/trx", ip);
@@ -247,3 +472,149 @@ impl Transaction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use sp_core::{
+        ed25519::{Pair, Public},
+        Pair as _,
+    };
+
+    use crate::{
+        generator::{block::header::Sign, encoding::Canonical, MerkelRoot},
+        tools::{
+            changeset::ChangeSet,
+            invalid::Invalid,
+            storage::{memory::MemoryStorage, Storage},
+            utxo::UTXO,
+        },
+    };
+
+    use super::{AmountError, Input, Output, Script, Transaction, Unspent, TRANSACTION_VERSION};
+
+    fn signer() -> Pair {
+        Pair::from_seed(&[1; 32])
+    }
+
+    fn utxo(hash: &str, value: i64) -> UTXO {
+        UTXO {
+            block: 1,
+            trx_hash: "a".repeat(64),
+            output_hash: "b".repeat(64),
+            unspent_hash: hash.to_string(),
+            unspent: Decimal::from(value),
+        }
+    }
+
+    //signed transaction of the signer that spends inputs and pays outputs
+    fn transaction(
+        inputs: Vec<UTXO>,
+        outputs: Vec<(Public, i64)>,
+        value: i64,
+        fee: i64,
+    ) -> Transaction {
+        let pair = signer();
+        let input = Input {
+            hash: inputs.canonical_hash("input"),
+            number: inputs.len() as u8,
+            utxos: inputs,
+        };
+        let output = Output::new(
+            outputs
+                .into_iter()
+                .map(|(wallet, value)| Unspent::new(&wallet, Decimal::from(value)))
+                .collect(),
+        );
+        let hash = MerkelRoot::make(vec![&input.hash, &output.hash]).remove(0);
+        Transaction {
+            signature: vec![Sign {
+                signatgure: pair.sign(hash.as_bytes()),
+                key: pair.public(),
+            }],
+            hash,
+            input,
+            output,
+            value: Decimal::from(value),
+            fee: Decimal::from(fee),
+            script: Script::Single,
+            date: "2024-01-02 03:04:05 UTC".to_string(),
+            version: TRANSACTION_VERSION,
+        }
+    }
+
+    fn recipient() -> Public {
+        Public::from_raw([2; 32])
+    }
+
+    #[test]
+    fn balanced_transaction_with_change_is_accepted() {
+        let change = signer().public();
+        let trx = transaction(
+            vec![utxo("u", 10)],
+            vec![(change, 3), (recipient(), 6)],
+            6,
+            1,
+        );
+        assert_eq!(trx.check(), Ok(()));
+        assert_eq!(trx.check_amounts(), Ok(()));
+    }
+
+    #[test]
+    fn transfer_to_signer_is_accepted() {
+        let trx = transaction(vec![utxo("u", 10)], vec![(signer().public(), 9)], 9, 1);
+        assert_eq!(trx.check_amounts(), Ok(()));
+    }
+
+    #[test]
+    fn over_spending_is_rejected() {
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 10)], 10, 1);
+        assert_eq!(trx.check_amounts(), Err(AmountError::Unbalanced));
+
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 100)], 100, 0);
+        assert_eq!(trx.check_amounts(), Err(AmountError::Unbalanced));
+    }
+
+    #[test]
+    fn negative_amounts_are_rejected() {
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 9)], -9, 1);
+        assert_eq!(trx.check_amounts(), Err(AmountError::NegativeValue));
+
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 11)], 11, -1);
+        assert_eq!(trx.check_amounts(), Err(AmountError::NegativeFee));
+
+        //a negative output can't pay a bigger output
+        let outputs = vec![(signer().public(), -5), (recipient(), 14)];
+        let trx = transaction(vec![utxo("u", 10)], outputs, 14, 1);
+        assert_eq!(trx.check_amounts(), Err(AmountError::NegativeOutput));
+    }
+
+    #[tokio::test]
+    async fn validate_rejects_crafted_transactions() {
+        let db = MemoryStorage::new();
+        let mut stored = ChangeSet::new();
+        stored.create(&signer().public(), utxo("u", 10));
+        db.commit(None, &stored).await.unwrap();
+
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 9)], 9, 1);
+        let mut changes = ChangeSet::new();
+        assert_eq!(trx.validate(&db, &mut changes).await, Ok(true));
+
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 1000)], 1000, 1);
+        let mut changes = ChangeSet::new();
+        let result = trx.validate(&db, &mut changes).await;
+        assert_eq!(
+            result,
+            Err(Invalid::Rejected(AmountError::Unbalanced.message()))
+        );
+        assert!(changes.spent.is_empty());
+
+        let trx = transaction(vec![utxo("u", 10)], vec![(recipient(), 9)], -9, 1);
+        let mut changes = ChangeSet::new();
+        let result = trx.validate(&db, &mut changes).await;
+        assert_eq!(
+            result,
+            Err(Invalid::Rejected(AmountError::NegativeValue.message()))
+        );
+    }
+}
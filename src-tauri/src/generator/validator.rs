@@ -1,21 +1,23 @@
 use libp2p::PeerId;
-use mongodb::Database;
 use serde::{Deserialize, Serialize};
 use sp_core::ed25519::Public;
 
-use crate::tools::waiting::Waiting;
+use crate::tools::{storage::Storage, waiting::Waiting};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Validator {
     pub peerid: PeerId,
     pub relay: PeerId,
     pub wallet: Public,
     pub waiting: u64,
+    //paused validator keeps its registration but is not elected as leader
+    #[serde(default)]
+    pub paused: bool,
 }
 
 impl Validator {
-    pub async fn new<'a>(
-        db: &'a Database,
+    pub async fn new<'a, S: Storage>(
+        db: &'a S,
         peerid: PeerId,
         relay: PeerId,
         wallet: Public,
@@ -27,6 +29,7 @@ impl Validator {
                 relay,
                 wallet,
                 waiting,
+                paused: false,
             }),
             Err(e) => Err(e),
         }
@@ -0,0 +1,65 @@
+use centichain_keypair::CentichainKey;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sp_core::{
+    ed25519::{Public, Signature},
+    Pair,
+};
+
+use super::{block::header::Sign, encoding::Canonical};
+
+// Vote of a validator about the new leader when the current leader left its turn
+// round is the height of the last block, so votes of former votings are not counted
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Vote {
+    pub voter: PeerId,
+    pub candidate: PeerId,
+    pub round: u64,
+    pub signature: Sign,
+}
+
+impl Vote {
+    // Make a vote that is signed by wallet of the voter
+    pub fn new<'a>(
+        voter: PeerId,
+        candidate: PeerId,
+        round: u64,
+        wallet: &Public,
+        private: &String,
+    ) -> Result<Self, &'a str> {
+        let mut vote = Self {
+            voter,
+            candidate,
+            round,
+            signature: Sign {
+                signatgure: Signature::from_raw([0; 64]),
+                key: *wallet,
+            },
+        };
+        match CentichainKey::signing(private, &vote.hash()) {
+            Ok(signed) => {
+                vote.signature.signatgure = signed;
+                Ok(vote)
+            }
+            Err(_) => Err("Error during signing the vote-(generator/vote 44)"),
+        }
+    }
+
+    fn hash(&self) -> String {
+        self.canonical_hash("vote")
+    }
+
+    // Check signature of the vote (the wallet must be wallet of the voter in validators)
+    pub fn verify(&self) -> bool {
+        sp_core::ed25519::Pair::verify(&self.signature.signatgure, self.hash(), &self.signature.key)
+    }
+}
+
+// Fields of the vote that its signature commits to
+impl Canonical for Vote {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.voter.encode(out);
+        self.candidate.encode(out);
+        self.round.encode(out);
+    }
+}
@@ -12,10 +12,20 @@ use tauri_plugin_updater::UpdaterExt;
 use tools::{
     exit::exit,
     for_front::{
-        blocks::latest_blocks, centies::sum_centies, make_trx::send_transaction,
+        blocks::{chain_heights, latest_blocks, merkle_proof},
+        centies::{sum_centies, wallet_utxos},
+        evidence::list_evidence,
+        import::import_dump,
+        make_trx::send_transaction,
+        membership::{pause_validator, resume_validator},
         mongodb::mongodb_download,
+        receipt::{export_receipt, verify_receipt},
+        revert::revert_chain,
     },
 };
+// storage of tools that run consensus and validation code without a mongod
+#[cfg(feature = "memory-storage")]
+pub use tools::storage::{memory::MemoryStorage, Storage};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -29,9 +39,19 @@ pub fn run() {
             start,
             exit,
             sum_centies,
+            wallet_utxos,
             latest_blocks,
+            chain_heights,
+            merkle_proof,
+            export_receipt,
+            verify_receipt,
             send_transaction,
             mongodb_download,
+            import_dump,
+            revert_chain,
+            pause_validator,
+            resume_validator,
+            list_evidence,
             generate_keys,
             check_for_updates,
         ])
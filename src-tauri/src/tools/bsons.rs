@@ -1,67 +1,152 @@
-use std::{fs::File, io::BufReader};
-
-use mongodb::{
-    bson::{from_document, Document},
-    Collection, Database,
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
 };
+
+use mongodb::bson::{from_document, Document};
 use tauri::Emitter;
 
-use crate::generator::transaction::Transaction;
+use crate::generator::{
+    block::block::Block, mempool::Mempool, transaction::Transaction, validator::Validator,
+};
+
+use super::{changeset::ChangeSet, storage::Storage, utxo::Person};
 
 pub struct Bson;
 
 impl Bson {
-    pub async fn add<'a>(
-        db: Option<&Database>,
-        collection_name: Option<&str>,
-        mut mempool: Option<&mut Vec<Transaction>>,
-        bson: &str,
+    // Import a MongoDB dump directory (BSON files of Centichain database) into the storage
+    pub async fn import<'a, S: Storage>(
+        db: &'a S,
+        dir: &str,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                let mut is_err = None;
+                for entry in entries {
+                    let path = entry.unwrap().path();
+                    let file_name = path.file_name().unwrap().to_str().unwrap();
+                    let collection_name = path.file_stem().unwrap().to_str().unwrap();
+                    if file_name.ends_with(".bson") {
+                        let bson_addr = path.to_str().unwrap();
+                        match Self::add(db, collection_name, bson_addr, window).await {
+                            Ok(_) => {}
+                            Err(e) => {
+                                is_err.get_or_insert(e);
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                match is_err {
+                    //utxos of the dump may be waiting in the cache for a block
+                    None => match db.flush().await {
+                        Ok(_) => Ok(window.emit("status", "Dump imported successfully").unwrap()),
+                        Err(e) => Err(e),
+                    },
+                    Some(e) => Err(e),
+                }
+            }
+            Err(_) => Err("Dump directory address is incorrect!-(tools/bsons 53)"),
+        }
+    }
+
+    // Insert documents of a BSON file into the storage based on the collection that the file was dumped from
+    pub async fn add<'a, S: Storage>(
+        db: &'a S,
+        collection_name: &str,
+        bson_addr: &str,
         window: &tauri::Window,
     ) -> Result<(), &'a str> {
         // Open the BSON file located at the specified address
+        let open_file = File::open(Path::new(bson_addr));
+        match open_file {
+            Ok(file) => {
+                let mut reader = BufReader::new(file);
+                let mut is_err = None;
+
+                // Read documents from the BSON file and insert them into the storage
+                // collections that validators don't use (like relay's collections) will be skipped
+                while let Ok(doc) = Document::from_reader(&mut reader) {
+                    let insertion = match collection_name {
+                        "Blocks" => match from_document::<Block>(doc) {
+                            Ok(block) => db.insert_block(&block).await,
+                            Err(_) => Err("Block of the dump is corrupt-(tools/bsons 77)"),
+                        },
+                        "UTXOs" => {
+                            // Dumps keep utxos of a wallet in one document, they are committed as new utxos of the wallet
+                            let person: Person = match from_document(doc) {
+                                Ok(person) => person,
+                                Err(_) => {
+                                    is_err.get_or_insert(
+                                        "UTXOs of the dump are corrupt-(tools/bsons 85)",
+                                    );
+                                    break;
+                                }
+                            };
+                            let mut changes = ChangeSet::new();
+                            for utxo in person.utxos {
+                                changes.create(&person.wallet, utxo);
+                            }
+                            db.commit(None, &changes).await
+                        }
+                        "validators" => match from_document::<Validator>(doc) {
+                            Ok(validator) => db.insert_validator(&validator).await,
+                            Err(_) => Err("Validator of the dump is corrupt-(tools/bsons 98)"),
+                        },
+                        _ => Ok(()),
+                    };
+
+                    if let Err(e) = insertion {
+                        is_err.get_or_insert(e);
+                        break;
+                    }
+                }
+
+                match is_err {
+                    None => {
+                        // Emit a status event to the window
+                        window
+                            .emit("status", format!("{} Synced", collection_name))
+                            .unwrap();
+                        Ok(())
+                    }
+                    Some(e) => Err(e),
+                }
+            }
+            Err(_e) => Err("Your file address is incorrect!-(tools/bsons 120)"),
+        }
+    }
+
+    // Treat the BSON file as a transactions file and insert the transactions into the mempool
+    pub async fn add_mempool<'a>(
+        mempool: &mut Mempool,
+        bson: &str,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
         let bson_addr = format!("./etc/dump/Centichain/{}", bson);
         let open_file = File::open(bson_addr);
         match open_file {
             Ok(file) => {
                 let mut reader = BufReader::new(file);
 
-                // If a database is provided, remove all documents from the specified collection
-                // and insert documents from the BSON file into the collection
-                if let Some(db) = db {
-                    let collection_name = collection_name.unwrap();
-                    
-                    // Drop the collection if it exists
-                    db.collection::<Document>(collection_name).drop().await.ok();
-                    
-                    // Create a new collection
-                    let collection: Collection<Document> = db.collection(collection_name);
-                    
-                    // Read documents from the BSON file and insert them into the collection
-                    while let Ok(doc) = Document::from_reader(&mut reader) {
-                        collection.insert_one(doc).await.unwrap();
-                    }
-                    
-                    // Emit a status event to the window
-                    window
-                        .emit("status", format!("{} Synced", collection_name))
-                        .unwrap();
-                    Ok(())
-                } else {
-                    // If no database is provided, treat the BSON file as a transactions file
-                    // and insert the transactions into the mempool
-                    if let Some(ref mut orig_mempool) = mempool {
-                        // Read documents from the BSON file and convert them to transactions
-                        while let Ok(doc) = Document::from_reader(&mut reader) {
-                            let transaction: Transaction = from_document(doc).unwrap();
-                            orig_mempool.push(transaction);
+                // Read documents from the BSON file and convert them to transactions
+                while let Ok(doc) = Document::from_reader(&mut reader) {
+                    match from_document::<Transaction>(doc) {
+                        Ok(transaction) => {
+                            let _ = mempool.insert(transaction);
+                        }
+                        Err(_) => {
+                            return Err("Transaction of the file is corrupt-(tools/bsons 143)")
                         }
-                        Ok(window.emit("mempool", orig_mempool.clone()).unwrap())
-                    } else {
-                        Err("You dont set mempool!")
                     }
                 }
+                Ok(window.emit("mempool", mempool.transactions()).unwrap())
             }
-            Err(_e) => Err("Your file address is incorrect!-(tools/bsons 57)"),
+            Err(_e) => Err("Your file address is incorrect!-(tools/bsons 149)"),
         }
     }
 }
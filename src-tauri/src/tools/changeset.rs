@@ -0,0 +1,86 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sp_core::ed25519::Public;
+
+use crate::generator::validator::Validator;
+
+use super::utxo::UTXO;
+
+// Staged state changes of a block (or of a transaction that enters mempool)
+// validation only writes into the change set and storage applies it at once in commit
+// so a rejected block never touches the UTXO set or validators
+//it is also the undo record of a block (see tools/undo)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub spent: Vec<(Public, UTXO)>,
+    pub created: Vec<(Public, UTXO)>,
+    pub validators: Vec<Validator>, //validators after updating their waiting
+    pub previous_validators: Vec<Validator>, //validators before updating their waiting
+}
+
+impl ChangeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Check if the utxo has been spent by an earlier transaction of this change set
+    pub fn is_spent(&self, unspent_hash: &str) -> bool {
+        self.spent
+            .iter()
+            .any(|(_, u)| u.unspent_hash == unspent_hash)
+    }
+
+    pub fn spend(&mut self, wallet: &Public, utxo: &UTXO) {
+        self.spent.push((*wallet, utxo.clone()));
+    }
+
+    pub fn create(&mut self, wallet: &Public, utxo: UTXO) {
+        self.created.push((*wallet, utxo));
+    }
+
+    // Stage the new state of a validator (later update of the same validator replaces the former one)
+    // the state before the first update is kept for undoing
+    pub fn update_validator(&mut self, previous: &Validator, validator: Validator) {
+        if !self
+            .previous_validators
+            .iter()
+            .any(|v| v.peerid == previous.peerid)
+        {
+            self.previous_validators.push(previous.clone());
+        }
+        self.validators.retain(|v| v.peerid != validator.peerid);
+        self.validators.push(validator);
+    }
+
+    // Make the change set that undoes this one
+    pub fn inverse(&self) -> Self {
+        Self {
+            spent: self.created.clone(),
+            created: self.spent.clone(),
+            validators: self.previous_validators.clone(),
+            previous_validators: self.validators.clone(),
+        }
+    }
+
+    // Sum of created minus spent values for each wallet to maintain balances
+    pub fn balance_changes(&self) -> Vec<(Public, Decimal)> {
+        let mut balances: Vec<(Public, Decimal)> = Vec::new();
+        let created = self.created.iter().map(|(w, u)| (w, u.unspent));
+        let spent = self.spent.iter().map(|(w, u)| (w, -u.unspent));
+        for (wallet, value) in created.chain(spent) {
+            match balances.iter_mut().find(|(w, _)| w == wallet) {
+                Some((_, balance)) => *balance += value,
+                None => balances.push((*wallet, value)),
+            }
+        }
+        balances
+    }
+
+    // Check if utxos of the wallet change by committing this change set
+    pub fn touches(&self, wallet: &Public) -> bool {
+        self.spent
+            .iter()
+            .chain(self.created.iter())
+            .any(|(w, _)| w == wallet)
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{SubsecRound, Utc};
+use libp2p::{gossipsub::IdentTopic, PeerId};
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::{
+    events::{gossip_messages::handler::GossipMessages, node::Node},
+    generator::{block::block::Block, leader::Leader, mempool::Mempool, HashMaker},
+};
+
+use super::{
+    invalid::Invalid,
+    storage::Storage,
+    trun_sync::{Sync, Turn},
+    wrongdoer::WrongDoer,
+};
+
+// Misbehaviour of a validator with the signed blocks that prove it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Offence {
+    //two different blocks at the same height that are signed by the validator
+    DoubleSign { first: Block, second: Block },
+    //signed block that is not valid on its previous block
+    InvalidBlock { block: Block },
+    //signed block of a validator that was not the leader
+    WrongLeader { block: Block, leader: PeerId },
+}
+
+impl Offence {
+    fn blocks(&self) -> Vec<&Block> {
+        match self {
+            Self::DoubleSign { first, second } => vec![first, second],
+            Self::InvalidBlock { block } => vec![block],
+            Self::WrongLeader { block, .. } => vec![block],
+        }
+    }
+
+    fn offender(&self) -> PeerId {
+        self.blocks()[0].header.validator
+    }
+
+    fn cause(&self) -> String {
+        match self {
+            Self::DoubleSign { first, .. } => {
+                format!("double signing at block {}", first.header.number)
+            }
+            Self::InvalidBlock { block } => format!("invalid block {}", block.header.number),
+            Self::WrongLeader { block, leader } => format!(
+                "block {} while the leader was {}",
+                block.header.number, leader
+            ),
+        }
+    }
+}
+
+// Evidence is persisted and gossiped, and every node checks it with its own state before removing the wrongdoer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Evidence {
+    pub id: String,
+    pub wrongdoer: WrongDoer,
+    pub offence: Offence,
+    pub reporter: PeerId,
+    pub date: String,
+}
+
+impl Evidence {
+    pub fn new(offence: Offence, reporter: &PeerId) -> Self {
+        let offender = offence.offender();
+        Self {
+            id: HashMaker::generate(&serde_json::to_string(&offence).unwrap()),
+            wrongdoer: WrongDoer::new(offender, offence.cause()),
+            offence,
+            reporter: *reporter,
+            date: Utc::now().round_subsecs(0).to_string(),
+        }
+    }
+
+    // Check the evidence independently
+    // blocks must be signed by the wallet of the wrongdoer in validators and the offence must be true for this node
+    pub async fn verify<'a, S: Storage>(
+        &self,
+        db: &'a S,
+        last_block: &Vec<Block>,
+        leader: &Leader,
+        window: &tauri::Window,
+    ) -> Result<(), Invalid<'a>> {
+        let offender = match db.find_validator(&self.wrongdoer.peerid).await {
+            Ok(Some(validator)) => validator,
+            Ok(None) => {
+                return Err(Invalid::Rejected(
+                    "Wrongdoer of the evidence is not a validator.",
+                ))
+            }
+            Err(e) => return Err(Invalid::Storage(e)),
+        };
+        for block in self.offence.blocks() {
+            if block.header.validator != offender.peerid
+                || block.header.signature.key != offender.wallet
+                || !block.check_signature()
+            {
+                return Err(Invalid::Rejected(
+                    "Blocks of the evidence are not signed by the wrongdoer.",
+                ));
+            }
+        }
+
+        //invalid block and wrong leader can be checked only on the tip of this node
+        let tip = match last_block.first() {
+            Some(tip) => tip,
+            None => {
+                return Err(Invalid::Rejected(
+                    "Evidence can't be checked without a chain.",
+                ))
+            }
+        };
+        match &self.offence {
+            Offence::DoubleSign { first, second } => {
+                if first.header.number == second.header.number
+                    && first.header.hash != second.header.hash
+                {
+                    Ok(())
+                } else {
+                    Err(Invalid::Rejected(
+                        "Blocks of the evidence are not at the same height.",
+                    ))
+                }
+            }
+            Offence::InvalidBlock { block } => {
+                if block.header.previous != tip.header.hash {
+                    return Err(Invalid::Rejected(
+                        "Evidence can't be checked with the chain of this node.",
+                    ));
+                }
+                //validation on a copy of the state doesn't change mempool, turn and database
+                match block
+                    .validation(
+                        &mut vec![tip.clone()],
+                        db,
+                        &mut Mempool::new(),
+                        window,
+                        &mut Turn::new(),
+                        &mut Sync::new(),
+                    )
+                    .await
+                {
+                    Ok(_) => Err(Invalid::Rejected("Block of the evidence is valid.")),
+                    Err(Invalid::Rejected(_)) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+            Offence::WrongLeader {
+                block,
+                leader: evidence_leader,
+            } => {
+                if block.header.previous != tip.header.hash {
+                    return Err(Invalid::Rejected(
+                        "Evidence can't be checked with the chain of this node.",
+                    ));
+                }
+                match leader.peerid {
+                    Some(current) if current == *evidence_leader && current != offender.peerid => {
+                        Ok(())
+                    }
+                    _ => Err(Invalid::Rejected(
+                        "Wrongdoer of the evidence was the leader.",
+                    )),
+                }
+            }
+        }
+    }
+
+    // Report misbehaviour that this node found (it is checked, stored and gossiped and then the wrongdoer is removed)
+    pub async fn report<'n, S: Storage>(self, node: &mut Node<'n, S>) -> Result<(), &'n str> {
+        let window = node.window;
+        match self
+            .verify(node.db, node.last_block, node.leader, window)
+            .await
+        {
+            Ok(_) => {}
+            Err(Invalid::Rejected(e)) => return Ok(window.emit("status", e).unwrap()),
+            Err(Invalid::Storage(e)) => return Err(e),
+        }
+
+        let message = GossipMessages::Evidence(self.clone());
+        let str_message = serde_json::to_string(&message).unwrap();
+        //the wrongdoer is removed even if there is no peer to gossip the evidence
+        if node
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(IdentTopic::new("validator"), str_message)
+            .is_err()
+        {
+            window
+                .emit("status", "Evidence could not be gossiped.")
+                .unwrap();
+        }
+        self.punish(node.db, node.turn, node.sync_state, window)
+            .await
+    }
+
+    // Handle evidence of other validators
+    pub async fn handle<'n, S: Storage>(self, node: &mut Node<'n, S>) -> Result<(), &'n str> {
+        let window = node.window;
+        match node.db.find_evidence(&self.id).await {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => match self
+                .verify(node.db, node.last_block, node.leader, window)
+                .await
+            {
+                Ok(_) => {
+                    self.punish(node.db, node.turn, node.sync_state, window)
+                        .await
+                }
+                Err(Invalid::Rejected(e)) => Ok(window
+                    .emit("status", format!("Evidence rejected: {}", e))
+                    .unwrap()),
+                Err(Invalid::Storage(e)) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    // Store the evidence and remove the wrongdoer from validators
+    async fn punish<'a, S: Storage>(
+        self,
+        db: &'a S,
+        turn: &mut Turn,
+        sync_state: &mut Sync,
+        window: &tauri::Window,
+    ) -> Result<(), &'a str> {
+        match db.insert_evidence(&self).await {
+            Ok(_) => {
+                match WrongDoer::remove(db, self.wrongdoer.peerid, turn, sync_state, window).await {
+                    Ok(peer) => Ok(window
+                        .emit(
+                            "status",
+                            format!(
+                                "This wrongdoer removed: {} ({})",
+                                peer, self.wrongdoer.cause
+                            ),
+                        )
+                        .unwrap()),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -1,4 +1,9 @@
+use crate::generator::membership::{Action, Membership};
+
+//a running validator gossips its exit message first and then the event loop closes the app
 #[tauri::command]
 pub async fn exit() {
-    std::process::exit(0)
+    if !Membership::request(Action::Exit) {
+        std::process::exit(0)
+    }
 }
@@ -1,36 +1,64 @@
-use libp2p::futures::StreamExt;
-use mongodb::{
-    bson::{doc, from_document, Document},
-    options::FindOptions,
-    Collection,
-};
+use serde::Serialize;
 
-use crate::{events::db::DatabseConnection, generator::block::block::Block};
+use crate::{
+    events::db::DatabseConnection,
+    generator::{
+        block::{attestation::Finality, block::Block},
+        merkle::Proof,
+    },
+    tools::storage::Storage,
+};
 
 #[tauri::command]
 pub async fn latest_blocks(page: u64) -> Vec<Block> {
     match DatabseConnection::connect().await {
         Ok(db) => {
-            let mut latests = Vec::new();
-            let collection: Collection<Document> = db.collection("Blocks");
-            let sort = doc! {"header.number": -1}; // Sort by block number in descending order
-            let skip = (page * 4) - 4; // Calculate the number of documents to skip
-            let options = FindOptions::builder()
-                .sort(sort)
-                .skip(skip)
-                .limit(4) // Limit the result to 10 documents
-                .build();
-            let cursor = collection.find(doc! {}).with_options(options).await;
-            if let Ok(mut finder) = cursor {
-                while let Some(Ok(doc)) = finder.next().await {
-                    let block: Block = from_document(doc).unwrap(); // Convert document to Block
-                    latests.push(block);
-                }
-                latests
-            } else {
-                latests
+            let skip = (page * 4) - 4; // Calculate the number of blocks to skip
+                                       // Blocks sorted by number in descending order and limited to 4 blocks
+            match db.latest_blocks(skip, 4).await {
+                Ok(latests) => latests,
+                Err(_e) => Vec::new(),
             }
         }
         Err(_e) => Vec::new(), // Return an empty vector if the connection fails
     }
 }
+
+//height of the chain tip and height of the last final block (wallets wait for finality)
+#[derive(Debug, Serialize)]
+pub struct Heights {
+    head: u64,
+    finalized: u64,
+}
+
+#[tauri::command]
+pub async fn chain_heights() -> Heights {
+    let mut heights = Heights {
+        head: 0,
+        finalized: 0,
+    };
+    if let Ok(db) = DatabseConnection::connect().await {
+        if let Ok(Some(block)) = db.last_block().await {
+            heights.head = block.header.number;
+        }
+        if let Ok(finalized) = Finality::finalized(&db).await {
+            heights.finalized = finalized;
+        }
+    }
+    heights
+}
+
+//inclusion proof of a transaction (or coinbase) in the block that wallets check with merkel of the header
+#[tauri::command]
+pub async fn merkle_proof(number: u64, hash: String) -> Option<Proof> {
+    match DatabseConnection::connect().await {
+        Ok(db) => match db.find_block(number).await {
+            Ok(Some(block)) => match (block.proof(&hash), block.header.merkle_root()) {
+                (Some(proof), Some(root)) if proof.verify(root) => Some(proof),
+                _ => None,
+            },
+            _ => None,
+        },
+        Err(_e) => None,
+    }
+}
@@ -1,26 +1,37 @@
-use mongodb::{
-    bson::{doc, from_document, Document},
-    Collection,
-};
-use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use sp_core::ed25519::Public;
 
-use crate::{events::db::DatabseConnection, tools::utxo::Person};
+use crate::{
+    events::db::DatabseConnection,
+    tools::{storage::Storage, utxo::UTXO},
+};
 
 #[tauri::command]
 pub async fn sum_centies(wallet: String) -> String {
     match DatabseConnection::connect().await {
         Ok(db) => {
-            let collection: Collection<Document> = db.collection("UTXOs");
-            let filter = doc! {"wallet": wallet};
-            let query = collection.find_one(filter).await;
-            if let Ok(Some(doc)) = query {
-                let person: Person = from_document(doc).unwrap();
-                let sum: Decimal = person.utxos.iter().map(|utxo| utxo.unspent).sum();
-                sum.to_string()
-            } else {
-                "0.0".to_string()
+            let query = match Public::from_str(&wallet) {
+                Ok(public) => db.balance(&public).await,
+                Err(_) => Err("Wallet address is incorrect!"),
+            };
+            match query {
+                Ok(sum) => sum.to_string(),
+                Err(_) => "0.0".to_string(),
             }
         }
         Err(e) => e.to_string(),
     }
 }
+
+//utxos of the wallet that are found by the wallet index of utxo set
+#[tauri::command]
+pub async fn wallet_utxos(wallet: String) -> Vec<UTXO> {
+    match DatabseConnection::connect().await {
+        Ok(db) => match Public::from_str(&wallet) {
+            Ok(public) => db.wallet_utxos(&public).await.unwrap_or_default(),
+            Err(_) => Vec::new(),
+        },
+        Err(_e) => Vec::new(),
+    }
+}
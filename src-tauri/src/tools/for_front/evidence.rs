@@ -0,0 +1,16 @@
+use crate::{
+    events::db::DatabseConnection,
+    tools::{evidence::Evidence, storage::Storage},
+};
+
+//evidence of wrongdoers that this node stored (for operators)
+#[tauri::command]
+pub async fn list_evidence() -> Vec<Evidence> {
+    match DatabseConnection::connect().await {
+        Ok(db) => match db.evidence().await {
+            Ok(evidence) => evidence,
+            Err(_e) => Vec::new(),
+        },
+        Err(_e) => Vec::new(),
+    }
+}
@@ -0,0 +1,16 @@
+use tauri::Emitter;
+
+use crate::{events::db::DatabseConnection, tools::bsons::Bson};
+
+//import an existing MongoDB dump (BSON files) into the storage that is selected in settings
+//it is useful for migrating a validator from mongodb to the embedded storage
+#[tauri::command]
+pub async fn import_dump(path: String, window: tauri::Window) {
+    match DatabseConnection::connect().await {
+        Ok(db) => match Bson::import(&db, &path, &window).await {
+            Ok(_) => {}
+            Err(e) => window.emit("error", e).unwrap(),
+        },
+        Err(e) => window.emit("error", e).unwrap(),
+    }
+}
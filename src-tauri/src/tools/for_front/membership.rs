@@ -0,0 +1,18 @@
+use tauri::Emitter;
+
+use crate::generator::membership::{Action, Membership};
+
+//skip turns of leadership during maintenance without losing registration
+#[tauri::command]
+pub async fn pause_validator(window: tauri::Window) {
+    if !Membership::request(Action::Pause) {
+        window.emit("error", "Validator is not running").unwrap();
+    }
+}
+
+#[tauri::command]
+pub async fn resume_validator(window: tauri::Window) {
+    if !Membership::request(Action::Resume) {
+        window.emit("error", "Validator is not running").unwrap();
+    }
+}
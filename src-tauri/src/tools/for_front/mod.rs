@@ -1,4 +1,9 @@
 pub mod blocks;
 pub mod centies;
+pub mod evidence;
+pub mod import;
 pub mod make_trx;
+pub mod membership;
 pub mod mongodb;
+pub mod receipt;
+pub mod revert;
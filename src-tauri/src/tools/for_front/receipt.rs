@@ -0,0 +1,88 @@
+use std::fs;
+
+use tauri::Emitter;
+
+use crate::{
+    events::db::DatabseConnection,
+    tools::receipt::{Receipt, Signer},
+};
+
+//write the inclusion receipt of the transaction to a json file
+#[tauri::command]
+pub async fn export_receipt(hash: String, path: String, window: tauri::Window) {
+    match DatabseConnection::connect().await {
+        Ok(db) => match Receipt::new(&db, &hash).await {
+            Ok(receipt) => {
+                let json = serde_json::to_string_pretty(&receipt).unwrap();
+                match fs::write(&path, json) {
+                    Ok(_) => window
+                        .emit("status", format!("Receipt saved: {}", path))
+                        .unwrap(),
+                    Err(_) => window
+                        .emit("error", "Error while writing the receipt file")
+                        .unwrap(),
+                }
+            }
+            Err(e) => window.emit("error", e).unwrap(),
+        },
+        Err(e) => window.emit("error", e).unwrap(),
+    }
+}
+
+//check a receipt file and its block with the chain of the node
+//(without a database only the receipt itself is checked and its signer is returned as untrusted)
+#[tauri::command]
+pub async fn verify_receipt(path: String, window: tauri::Window) -> Option<Signer> {
+    let receipt: Receipt = match fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(receipt) => receipt,
+            Err(_) => {
+                window.emit("status", "Receipt file is incorrect").unwrap();
+                return None;
+            }
+        },
+        Err(_) => {
+            window.emit("status", "Receipt file not found").unwrap();
+            return None;
+        }
+    };
+    match DatabseConnection::connect().await {
+        Ok(db) => emit_signer(receipt.check(&db).await, &window),
+        Err(_) => emit_signer(receipt.verify(), &window),
+    }
+}
+
+//tell the operator how far the receipt can be trusted
+fn emit_signer(checked: Result<Signer, &str>, window: &tauri::Window) -> Option<Signer> {
+    match checked {
+        Ok(signer) => {
+            let status = if !signer.in_chain {
+                format!(
+                    "Receipt is correct but block {} is not in the chain of this node",
+                    signer.number
+                )
+            } else if !signer.registered {
+                format!(
+                    "Block {} of the receipt is in the chain, its signer is not a validator now",
+                    signer.number
+                )
+            } else if signer.finalized {
+                format!(
+                    "Block {} of the receipt is in the chain and final",
+                    signer.number
+                )
+            } else {
+                format!(
+                    "Block {} of the receipt is in the chain but not final yet",
+                    signer.number
+                )
+            };
+            window.emit("status", status).unwrap();
+            Some(signer)
+        }
+        Err(e) => {
+            window.emit("status", e).unwrap();
+            None
+        }
+    }
+}
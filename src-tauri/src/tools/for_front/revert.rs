@@ -0,0 +1,20 @@
+use tauri::Emitter;
+
+use crate::{events::db::DatabseConnection, tools::undo::Undo};
+
+//revert stored chain to the block of the height for operator recovery (node must be stopped)
+#[tauri::command]
+pub async fn revert_chain(height: u64, window: tauri::Window) {
+    match DatabseConnection::connect().await {
+        Ok(db) => match Undo::revert_to(&db, height).await {
+            Ok(count) => window
+                .emit(
+                    "status",
+                    format!("{} blocks reverted, chain tip: {}", count, height),
+                )
+                .unwrap(),
+            Err(e) => window.emit("error", e).unwrap(),
+        },
+        Err(e) => window.emit("error", e).unwrap(),
+    }
+}
@@ -0,0 +1,16 @@
+// Error of validating a block or a transaction
+// a rejection proves that the block or transaction is wrong, a storage error is a problem of this node
+// and it must not be blamed on the sender
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Invalid<'a> {
+    Rejected(&'a str),
+    Storage(&'a str),
+}
+
+impl<'a> Invalid<'a> {
+    pub fn message(&self) -> &'a str {
+        match self {
+            Self::Rejected(e) | Self::Storage(e) => e,
+        }
+    }
+}
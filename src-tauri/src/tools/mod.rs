@@ -1,8 +1,16 @@
 pub mod bsons;
+pub mod changeset;
 pub mod downloader;
+pub mod evidence;
 pub mod exit;
 pub mod for_front;
+pub mod invalid;
+pub mod receipt;
+pub mod scheduler;
+pub mod settings;
+pub mod storage;
 pub mod trun_sync;
+pub mod undo;
 pub mod utxo;
 pub mod waiting;
 pub mod wrongdoer;
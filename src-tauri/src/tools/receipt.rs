@@ -0,0 +1,114 @@
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sp_core::ed25519::Public;
+
+use crate::generator::{
+    block::{attestation::Finality, header::Header},
+    merkle::Proof,
+    transaction::Transaction,
+};
+
+use super::storage::Storage;
+
+// Self-contained proof that a transaction is in a block
+// header carries the validator signature and the merkle root, so the receipt is checked offline
+// (offline it only proves which wallet signed the block, so the signer and the block must be checked
+// with a trusted chain, see Receipt::check)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub transaction: Transaction,
+    pub header: Header,
+    pub proof: Proof,
+}
+
+// Block and signer of a correct receipt and what the chain of the node says about them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Signer {
+    pub number: u64,
+    pub block: String,
+    pub validator: PeerId,
+    pub wallet: Public,
+    //the block at the height in the chain of the node is the block of the receipt
+    pub in_chain: bool,
+    //the wallet is the wallet of the validator in validators of the node
+    pub registered: bool,
+    //the block is at or below the last final block of the node, so it is not reverted
+    pub finalized: bool,
+}
+
+impl Receipt {
+    // Make receipt of the transaction from its stored block
+    pub async fn new<'a, S: Storage>(db: &'a S, hash: &String) -> Result<Self, &'a str> {
+        let block = match db.transaction_block(hash).await {
+            Ok(Some(block)) => block,
+            Ok(None) => return Err("Transaction is not in any block."),
+            Err(e) => return Err(e),
+        };
+        if block.header.merkle_root().is_none() {
+            return Err("Block of the transaction was made before merkle roots of headers.");
+        }
+        match (
+            block.proof(hash),
+            block.body.transactions.iter().find(|trx| &trx.hash == hash),
+        ) {
+            (Some(proof), Some(transaction)) => Ok(Self {
+                transaction: transaction.clone(),
+                header: block.header.clone(),
+                proof,
+            }),
+            _ => Err("Error while making proof of the transaction-(tools/receipt 59)"),
+        }
+    }
+
+    // Check the receipt without storage and return its signer
+    // transaction, header and the path from the transaction to merkle root of the header must be correct,
+    // but anyone can sign a header, so the caller must check the signer wallet and the block hash
+    pub fn verify(&self) -> Result<Signer, &'static str> {
+        if let Err(e) = self.transaction.check() {
+            return Err(e);
+        }
+        if self.proof.leaf != self.transaction.hash {
+            return Err("Proof of the receipt is not for its transaction.");
+        }
+        if !self.header.check_signature() {
+            return Err("Header of the receipt has an incorrect hash or signature.");
+        }
+        match self.header.merkle_root() {
+            Some(root) if self.proof.verify(root) => Ok(Signer {
+                number: self.header.number,
+                block: self.header.hash.clone(),
+                validator: self.header.validator,
+                wallet: self.header.signature.key,
+                in_chain: false,
+                registered: false,
+                finalized: false,
+            }),
+            Some(_) => Err("Transaction of the receipt is not in merkle root of the header."),
+            None => Err("Header of the receipt has no merkle root."),
+        }
+    }
+
+    // Check the receipt with the chain of the node
+    // the block of the receipt must be the stored block at its height to be trusted
+    pub async fn check<'a, S: Storage>(&self, db: &'a S) -> Result<Signer, &'a str> {
+        let mut signer = match self.verify() {
+            Ok(signer) => signer,
+            Err(e) => return Err(e),
+        };
+        match db.find_block(signer.number).await {
+            Ok(Some(block)) => signer.in_chain = block.header.hash == signer.block,
+            Ok(None) => {}
+            Err(e) => return Err(e),
+        }
+        match db.find_validator(&signer.validator).await {
+            Ok(Some(validator)) => signer.registered = validator.wallet == signer.wallet,
+            Ok(None) => {}
+            Err(e) => return Err(e),
+        }
+        match Finality::finalized(db).await {
+            Ok(finalized) => signer.finalized = signer.in_chain && signer.number <= finalized,
+            Err(e) => return Err(e),
+        }
+        Ok(signer)
+    }
+}
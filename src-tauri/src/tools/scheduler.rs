@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use tokio::time::{sleep_until, Instant};
+
+//jobs that the event loop runs beside network events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Job {
+    UiRefresh,
+    LeaderTimeout,
+    RelayRedial,
+    MempoolExpiry,
+    BlockProduction,
+    Shutdown,
+}
+
+#[derive(Debug)]
+struct Entry {
+    job: Job,
+    at: Instant,
+    period: Option<Duration>,
+}
+
+//scheduler of delayed and periodic jobs
+//next() is awaited in tokio::select! with swarm events so jobs never block polling of the swarm
+#[derive(Debug)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    //run the job once after the delay (a job that is scheduled already gets the new time)
+    pub fn after(&mut self, job: Job, delay: Duration) {
+        self.cancel(job);
+        self.entries.push(Entry {
+            job,
+            at: Instant::now() + delay,
+            period: None,
+        });
+    }
+
+    //run the job every period
+    pub fn every(&mut self, job: Job, period: Duration) {
+        self.cancel(job);
+        self.entries.push(Entry {
+            job,
+            at: Instant::now() + period,
+            period: Some(period),
+        });
+    }
+
+    pub fn cancel(&mut self, job: Job) {
+        self.entries.retain(|e| e.job != job);
+    }
+
+    //wait for the earliest job and return it (periodic jobs are scheduled again)
+    //dropping the future before its time doesn't change jobs, so it is safe in select
+    pub async fn next(&mut self) -> Job {
+        let earliest = self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| e.at)
+            .map(|(i, e)| (i, e.at));
+        match earliest {
+            Some((i, at)) => {
+                sleep_until(at).await;
+                let entry = &mut self.entries[i];
+                let job = entry.job;
+                match entry.period {
+                    Some(period) => entry.at = Instant::now() + period,
+                    None => {
+                        self.entries.remove(i);
+                    }
+                }
+                job
+            }
+            //without any jobs it waits for ever and select gets swarm events
+            None => std::future::pending().await,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+//node settings that operator can set in settings.json next to the app
+//every missing field falls back to its default
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub storage: StorageKind,
+    pub mongodb_uri: String,
+    pub data_dir: String,
+    //more missing blocks than this makes syncing replace stored chain by the full snapshot
+    pub max_catchup_blocks: u64,
+    //count of utxos that are kept in the utxo cache of the node
+    pub utxo_cache_size: usize,
+    //count of transactions that mempool keeps
+    pub mempool_max_size: usize,
+    //seconds that a transaction can wait in mempool
+    pub mempool_ttl: u64,
+    //seconds that validators wait for block of the leader before voting for a new leader
+    pub leader_timeout: u64,
+    //count of transactions that makes the leader seal a block at once (a block can't have more than 51)
+    pub block_max_transactions: usize,
+    //seconds that the leader waits for more transactions before sealing a block that isn't full
+    pub block_max_wait: u64,
+    //seconds without any block that makes the leader seal an empty block (0 means no empty blocks)
+    pub heartbeat_interval: u64,
+    //count of known relays that must report a disconnected validator before it is removed
+    //(0 means the signed notice of a known relay is enough)
+    pub outnode_confirmations: usize,
+}
+
+//storage backend of the node
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum StorageKind {
+    MongoDB,
+    Embedded,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            storage: StorageKind::MongoDB,
+            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            data_dir: "./data".to_string(),
+            max_catchup_blocks: 1000,
+            utxo_cache_size: 100_000,
+            mempool_max_size: 5000,
+            mempool_ttl: 3600,
+            leader_timeout: 59,
+            block_max_transactions: 51,
+            block_max_wait: 10,
+            heartbeat_interval: 0,
+            outnode_confirmations: 0,
+        }
+    }
+}
+
+impl Settings {
+    //read settings.json and if it doesn't exist or is incorrect return default settings
+    pub fn load() -> Self {
+        match fs::read_to_string("settings.json") {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(settings) => settings,
+                Err(_) => Self::default(),
+            },
+            Err(_) => Self::default(),
+        }
+    }
+}
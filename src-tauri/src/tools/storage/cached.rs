@@ -0,0 +1,391 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use libp2p::PeerId;
+use rust_decimal::Decimal;
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{attestation::Certificate, block::Block, forks::MAX_REORG_DEPTH},
+        relay::Relay,
+        validator::Validator,
+    },
+    tools::{changeset::ChangeSet, evidence::Evidence, settings::Settings, utxo::UTXO},
+};
+
+use super::Storage;
+
+// Write-back utxo cache in front of the storage that block validation and mempool admission consult first
+// changes of utxos without a block (like utxos of a snapshot) are kept in memory and flushed at the next block commit,
+// so the storage is written once per block, changes of validators are written through
+#[derive(Debug)]
+pub struct Cached<S: Storage> {
+    inner: S,
+    capacity: usize,
+    data: Mutex<CacheData>,
+}
+
+#[derive(Debug, Default)]
+struct CacheData {
+    utxos: HashMap<String, Option<(Public, UTXO)>>, //None means the utxo was spent
+    order: VecDeque<String>,                        //cached unspent hashes from the oldest one
+    pending: ChangeSet,                             //changes that are not flushed yet
+    recent: VecDeque<(u64, ChangeSet)>, //changes of the last committed blocks for reverting them
+}
+
+impl CacheData {
+    //cache the entry and evict the oldest entries that are flushed
+    fn insert(&mut self, unspent_hash: &str, entry: Option<(Public, UTXO)>, capacity: usize) {
+        if self.utxos.insert(unspent_hash.to_string(), entry).is_none() {
+            self.order.push_back(unspent_hash.to_string());
+        }
+        let mut checked = 0;
+        while self.utxos.len() > capacity && checked < self.order.len() {
+            match self.order.pop_front() {
+                Some(hash) if self.is_pending(&hash) => self.order.push_back(hash),
+                Some(hash) => {
+                    self.utxos.remove(&hash);
+                }
+                None => break,
+            }
+            checked += 1;
+        }
+    }
+
+    fn remove(&mut self, unspent_hash: &str) {
+        if self.utxos.remove(unspent_hash).is_some() {
+            self.order.retain(|hash| hash != unspent_hash);
+        }
+    }
+
+    fn is_pending(&self, unspent_hash: &str) -> bool {
+        self.pending
+            .spent
+            .iter()
+            .chain(self.pending.created.iter())
+            .any(|(_, u)| u.unspent_hash == unspent_hash)
+    }
+
+    //keep spent and created utxos of the changes
+    fn apply(&mut self, changes: &ChangeSet, capacity: usize) {
+        for (_, utxo) in &changes.spent {
+            self.insert(&utxo.unspent_hash, None, capacity);
+        }
+        for (wallet, utxo) in &changes.created {
+            self.insert(&utxo.unspent_hash, Some((*wallet, utxo.clone())), capacity);
+        }
+    }
+
+    //forget utxos of the changes (storage may complete a failed commit later, so they can't be trusted)
+    fn forget(&mut self, changes: &ChangeSet) {
+        for (_, utxo) in changes.spent.iter().chain(changes.created.iter()) {
+            self.remove(&utxo.unspent_hash);
+        }
+    }
+}
+
+impl<S: Storage> Cached<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            capacity: Settings::load().utxo_cache_size,
+            data: Mutex::new(CacheData::default()),
+        }
+    }
+}
+
+impl<S: Storage> Storage for Cached<S> {
+    async fn clear(&self) -> Result<(), &'static str> {
+        *self.data.lock().unwrap() = CacheData::default();
+        self.inner.clear().await
+    }
+
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str> {
+        //changes of utxos without a block wait for the next block (or a flush when there are too many of them)
+        if block.is_none() && changes.validators.is_empty() {
+            let full = {
+                let mut data = self.data.lock().unwrap();
+                data.pending.spent.extend(changes.spent.iter().cloned());
+                data.pending.created.extend(changes.created.iter().cloned());
+                data.apply(changes, self.capacity);
+                data.pending.spent.len() + data.pending.created.len() >= self.capacity
+            };
+            return if full { self.flush().await } else { Ok(()) };
+        }
+
+        match self.flush().await {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+        match self.inner.commit(block, changes).await {
+            Ok(_) => {
+                let mut data = self.data.lock().unwrap();
+                data.apply(changes, self.capacity);
+                if let Some(block) = block {
+                    data.recent
+                        .push_back((block.header.number, changes.clone()));
+                    if data.recent.len() as u64 > MAX_REORG_DEPTH {
+                        data.recent.pop_front();
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.data.lock().unwrap().forget(changes);
+                Err(e)
+            }
+        }
+    }
+
+    //write changes that wait for the next block into the storage
+    async fn flush(&self) -> Result<(), &'static str> {
+        let pending = std::mem::take(&mut self.data.lock().unwrap().pending);
+        if pending.spent.is_empty() && pending.created.is_empty() {
+            return Ok(());
+        }
+        match self.inner.commit(None, &pending).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.data.lock().unwrap().forget(&pending);
+                Err(e)
+            }
+        }
+    }
+
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str> {
+        match self.flush().await {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+        let reverted = self.inner.revert_block(number).await;
+        let mut data = self.data.lock().unwrap();
+        let recent = match data.recent.back() {
+            Some((last, _)) if *last == number => data.recent.pop_back().map(|(_, c)| c),
+            _ => None,
+        };
+        match (&reverted, recent) {
+            //utxos that the block spent are unspent again and utxos that it created are spent
+            (Ok(_), Some(changes)) => data.apply(&changes.inverse(), self.capacity),
+            (Err(_), Some(changes)) => data.forget(&changes),
+            //changes of the block are not known, so utxos of the block and spent ones are forgotten
+            (_, None) => {
+                let stale: Vec<String> = data
+                    .utxos
+                    .iter()
+                    .filter(|(_, entry)| match entry {
+                        Some((_, utxo)) => utxo.block >= number,
+                        None => true,
+                    })
+                    .map(|(hash, _)| hash.clone())
+                    .collect();
+                for hash in stale {
+                    data.remove(&hash);
+                }
+            }
+        }
+        reverted
+    }
+
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str> {
+        self.inner.has_undo(number).await
+    }
+
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str> {
+        self.inner.insert_block(block).await
+    }
+
+    async fn last_block(&self) -> Result<Option<Block>, &'static str> {
+        self.inner.last_block().await
+    }
+
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str> {
+        self.inner.find_block(number).await
+    }
+
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str> {
+        self.inner.latest_blocks(skip, limit).await
+    }
+
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str> {
+        self.inner.transaction_block(hash).await
+    }
+
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str> {
+        {
+            let data = self.data.lock().unwrap();
+            if let Some(entry) = data.utxos.get(unspent_hash) {
+                return Ok(match entry {
+                    Some((owner, utxo)) if owner == wallet => Some(utxo.clone()),
+                    _ => None,
+                });
+            }
+        }
+
+        //missed utxos are cached if they exist
+        match self.inner.find_utxo(wallet, unspent_hash).await {
+            Ok(Some(utxo)) => {
+                self.data.lock().unwrap().insert(
+                    unspent_hash,
+                    Some((*wallet, utxo.clone())),
+                    self.capacity,
+                );
+                Ok(Some(utxo))
+            }
+            other => other,
+        }
+    }
+
+    //utxos and balances are read from the storage, so waiting changes are flushed first
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str> {
+        match self.flush().await {
+            Ok(_) => self.inner.wallet_utxos(wallet).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str> {
+        match self.flush().await {
+            Ok(_) => self.inner.balance(wallet).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str> {
+        self.inner.validators().await
+    }
+
+    async fn validators_count(&self) -> Result<u64, &'static str> {
+        self.inner.validators_count().await
+    }
+
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str> {
+        self.inner.find_validator(peerid).await
+    }
+
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        self.inner.insert_validator(validator).await
+    }
+
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        self.inner.update_validator(validator).await
+    }
+
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str> {
+        self.inner.remove_validators(peerid).await
+    }
+
+    async fn clear_validators(&self) -> Result<(), &'static str> {
+        self.inner.clear_validators().await
+    }
+
+    async fn relay(&self) -> Result<Option<Relay>, &'static str> {
+        self.inner.relay().await
+    }
+
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.inner.set_relay(relay).await
+    }
+
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str> {
+        self.inner.relays().await
+    }
+
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.inner.insert_relay(relay).await
+    }
+
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str> {
+        self.inner.remove_relay(addr).await
+    }
+
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str> {
+        self.inner.finality().await
+    }
+
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str> {
+        self.inner.set_finality(certificate).await
+    }
+
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str> {
+        self.inner.insert_evidence(evidence).await
+    }
+
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str> {
+        self.inner.find_evidence(id).await
+    }
+
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str> {
+        self.inner.evidence().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rust_decimal::Decimal;
+    use sp_core::ed25519::Public;
+
+    use crate::tools::{
+        changeset::ChangeSet,
+        storage::{memory::MemoryStorage, Storage},
+        utxo::UTXO,
+    };
+
+    use super::{CacheData, Cached};
+
+    fn cached(capacity: usize) -> Cached<MemoryStorage> {
+        Cached {
+            inner: MemoryStorage::new(),
+            capacity,
+            data: Mutex::new(CacheData::default()),
+        }
+    }
+
+    fn utxo(hash: &str) -> UTXO {
+        UTXO {
+            block: 1,
+            trx_hash: "trx".to_string(),
+            output_hash: "output".to_string(),
+            unspent_hash: hash.to_string(),
+            unspent: Decimal::from(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn utxos_without_block_wait_for_flush() {
+        let db = cached(10);
+        let wallet = Public::from_raw([1; 32]);
+        let mut changes = ChangeSet::new();
+        changes.create(&wallet, utxo("a"));
+        db.commit(None, &changes).await.unwrap();
+
+        assert!(db.find_utxo(&wallet, "a").await.unwrap().is_some());
+        assert!(db.inner.find_utxo(&wallet, "a").await.unwrap().is_none());
+
+        db.flush().await.unwrap();
+        assert!(db.inner.find_utxo(&wallet, "a").await.unwrap().is_some());
+        assert_eq!(db.balance(&wallet).await.unwrap(), Decimal::from(5));
+    }
+
+    #[test]
+    fn eviction_keeps_entries_that_are_not_flushed() {
+        let wallet = Public::from_raw([1; 32]);
+        let mut data = CacheData::default();
+        data.pending.create(&wallet, utxo("a"));
+        data.insert("a", Some((wallet, utxo("a"))), 2);
+        data.insert("b", Some((wallet, utxo("b"))), 2);
+        data.insert("c", Some((wallet, utxo("c"))), 2);
+
+        assert!(data.utxos.contains_key("a"));
+        assert!(!data.utxos.contains_key("b"));
+        assert!(data.utxos.contains_key("c"));
+    }
+}
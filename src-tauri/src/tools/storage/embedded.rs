@@ -0,0 +1,539 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use libp2p::PeerId;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sled::{
+    transaction::{ConflictableTransactionError, TransactionResult, TransactionalTree},
+    Db, Transactional, Tree,
+};
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{attestation::Certificate, block::Block},
+        relay::Relay,
+        validator::Validator,
+    },
+    tools::{changeset::ChangeSet, evidence::Evidence, undo::Undo, utxo::UTXO},
+};
+
+use super::Storage;
+
+//sled allows one open handle per data directory so the node and front commands share the handle of a directory
+static OPENED: Mutex<Vec<(PathBuf, Db)>> = Mutex::new(Vec::new());
+
+//embedded key-value storage in the data directory for running a validator without mongodb
+//blocks and undo records are keyed by big-endian number, utxos by unspent hash, validators by peerid and relays by address
+//wallet index is keyed by wallet and unspent hash and balances by wallet
+#[derive(Debug, Clone)]
+pub struct EmbeddedStorage {
+    db: Db,
+    blocks: Tree,
+    undo: Tree,
+    utxos: Tree,
+    wallet_utxos: Tree,
+    balances: Tree,
+    validators: Tree,
+    relay: Tree,
+    relays: Tree,
+    finality: Tree,
+    evidence: Tree,
+}
+
+//value of utxos tree
+#[derive(Debug, Serialize, Deserialize)]
+struct UtxoRecord {
+    wallet: Public,
+    utxo: UTXO,
+}
+
+impl EmbeddedStorage {
+    pub fn open<'a>(data_dir: &str) -> Result<Self, &'a str> {
+        let db = match Self::handle(data_dir) {
+            Ok(db) => db,
+            Err(e) => return Err(e),
+        };
+
+        let trees = (
+            db.open_tree("Blocks"),
+            db.open_tree("undo"),
+            db.open_tree("utxoset"),
+            db.open_tree("wallet_utxos"),
+            db.open_tree("balances"),
+            db.open_tree("validators"),
+            db.open_tree("relay"),
+            db.open_tree("relays"),
+            db.open_tree("finality"),
+            db.open_tree("evidence"),
+        );
+        match trees {
+            (
+                Ok(blocks),
+                Ok(undo),
+                Ok(utxos),
+                Ok(wallet_utxos),
+                Ok(balances),
+                Ok(validators),
+                Ok(relay),
+                Ok(relays),
+                Ok(finality),
+                Ok(evidence),
+            ) => Ok(Self {
+                db,
+                blocks,
+                undo,
+                utxos,
+                wallet_utxos,
+                balances,
+                validators,
+                relay,
+                relays,
+                finality,
+                evidence,
+            }),
+            _ => Err("Opening trees of embedded database problem-(tools/storage/embedded 98)"),
+        }
+    }
+
+    //handle of the data directory (it is opened once and then shared)
+    fn handle<'a>(data_dir: &str) -> Result<Db, &'a str> {
+        let path = match Path::new(data_dir).canonicalize() {
+            Ok(path) => path,
+            Err(_) => PathBuf::from(data_dir),
+        };
+        let mut opened = OPENED.lock().unwrap();
+        if let Some((_, db)) = opened.iter().find(|(dir, _)| *dir == path) {
+            return Ok(db.clone());
+        }
+        match sled::open(&path) {
+            Ok(db) => {
+                //a new directory has no canonical path before sled creates it
+                let path = path.canonicalize().unwrap_or(path);
+                opened.push((path, db.clone()));
+                Ok(db)
+            }
+            Err(_) => Err("Opening embedded database problem-(tools/storage/embedded 119)"),
+        }
+    }
+
+    //write and flush a record
+    async fn put_flushed<T: Serialize>(
+        &self,
+        tree: &Tree,
+        key: &[u8],
+        value: &T,
+    ) -> Result<(), &'static str> {
+        match Self::put(tree, key, value) {
+            Ok(_) => self.flush().await,
+            Err(e) => Err(e),
+        }
+    }
+
+    //flush written records to disk
+    async fn flush(&self) -> Result<(), &'static str> {
+        match self.db.flush_async().await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Flushing embedded database problem-(tools/storage/embedded 140)"),
+        }
+    }
+
+    fn put<T: Serialize>(tree: &Tree, key: &[u8], value: &T) -> Result<(), &'static str> {
+        let bytes = match serde_json::to_vec(value) {
+            Ok(bytes) => bytes,
+            Err(_) => return Err("Encoding a record problem-(tools/storage/embedded 147)"),
+        };
+        match tree.insert(key, bytes) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Writing into embedded database problem-(tools/storage/embedded 151)"),
+        }
+    }
+
+    //a corrupt record is an error and doesn't stop the node
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, &'static str> {
+        match serde_json::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(_) => Err("Corrupt record in embedded database-(tools/storage/embedded 159)"),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(tree: &Tree, key: &[u8]) -> Result<Option<T>, &'static str> {
+        match tree.get(key) {
+            Ok(Some(bytes)) => Self::decode(&bytes).map(Some),
+            Ok(None) => Ok(None),
+            Err(_) => Err("Reading from embedded database problem-(tools/storage/embedded 167)"),
+        }
+    }
+
+    fn all<T: DeserializeOwned>(tree: &Tree) -> Result<Vec<T>, &'static str> {
+        let mut items = Vec::new();
+        for item in tree.iter() {
+            match item {
+                Ok((_, bytes)) => match Self::decode(&bytes) {
+                    Ok(value) => items.push(value),
+                    Err(e) => return Err(e),
+                },
+                Err(_) => {
+                    return Err(
+                        "Reading from embedded database problem-(tools/storage/embedded 181)",
+                    )
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    //key of wallet index
+    fn wallet_key(wallet: &Public, unspent_hash: &str) -> Vec<u8> {
+        format!("{}/{}", wallet, unspent_hash).into_bytes()
+    }
+
+    //apply utxos, balances and validators of the change set inside a transaction
+    fn tx_apply(
+        (utxos, wallet_utxos, balances, validators): (
+            &TransactionalTree,
+            &TransactionalTree,
+            &TransactionalTree,
+            &TransactionalTree,
+        ),
+        changes: &ChangeSet,
+    ) -> Result<(), ConflictableTransactionError<()>> {
+        for (wallet, utxo) in &changes.spent {
+            utxos.remove(utxo.unspent_hash.as_bytes())?;
+            wallet_utxos.remove(Self::wallet_key(wallet, &utxo.unspent_hash))?;
+        }
+        for (wallet, utxo) in &changes.created {
+            let record = UtxoRecord {
+                wallet: *wallet,
+                utxo: utxo.clone(),
+            };
+            utxos.insert(
+                utxo.unspent_hash.as_bytes(),
+                serde_json::to_vec(&record).unwrap(),
+            )?;
+            wallet_utxos.insert(Self::wallet_key(wallet, &utxo.unspent_hash), &[])?;
+        }
+        for (wallet, value) in changes.balance_changes() {
+            let key = wallet.to_string();
+            let balance: Decimal = match balances.get(key.as_bytes())? {
+                Some(bytes) => match Self::decode(&bytes) {
+                    Ok(balance) => balance,
+                    Err(_) => return Err(ConflictableTransactionError::Abort(())),
+                },
+                None => Decimal::ZERO,
+            };
+            balances.insert(
+                key.as_bytes(),
+                serde_json::to_vec(&(balance + value)).unwrap(),
+            )?;
+        }
+        for validator in &changes.validators {
+            let key = validator.peerid.to_string();
+            if validators.get(key.as_bytes())?.is_some() {
+                validators.insert(key.as_bytes(), serde_json::to_vec(validator).unwrap())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Storage for EmbeddedStorage {
+    async fn clear(&self) -> Result<(), &'static str> {
+        let trees = [
+            &self.blocks,
+            &self.undo,
+            &self.utxos,
+            &self.wallet_utxos,
+            &self.balances,
+            &self.validators,
+            &self.finality,
+        ];
+        for tree in trees {
+            if tree.clear().is_err() {
+                return Err("Drop database error-(tools/storage/embedded 256)");
+            }
+        }
+        self.flush().await
+    }
+
+    //sled transaction over blocks, undo, utxos and validators trees makes the commit all or nothing
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str> {
+        let trees = (
+            &self.blocks,
+            &self.undo,
+            &self.utxos,
+            &self.wallet_utxos,
+            &self.balances,
+            &self.validators,
+        );
+        let result: TransactionResult<(), ()> = trees.transaction(
+            |(blocks, undo, utxos, wallet_utxos, balances, validators)| {
+                Self::tx_apply((utxos, wallet_utxos, balances, validators), changes)?;
+                if let Some(block) = block {
+                    let key = block.header.number.to_be_bytes();
+                    let record = Undo::new(block.header.number, changes);
+                    undo.insert(&key, serde_json::to_vec(&record).unwrap())?;
+                    blocks.insert(&key, serde_json::to_vec(block).unwrap())?;
+                }
+                Ok(())
+            },
+        );
+
+        match result {
+            Ok(_) => self.flush().await,
+            Err(_) => Err("Committing block changes problem-(tools/storage/embedded 287)"),
+        }
+    }
+
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str> {
+        let key = number.to_be_bytes();
+        let record: Undo = match Self::get(&self.undo, &key) {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return Err("Undo record of the block not found-(tools/storage/embedded 296)")
+            }
+            Err(e) => return Err(e),
+        };
+        let changes = record.changes.inverse();
+
+        let trees = (
+            &self.blocks,
+            &self.undo,
+            &self.utxos,
+            &self.wallet_utxos,
+            &self.balances,
+            &self.validators,
+        );
+        let result: TransactionResult<(), ()> = trees.transaction(
+            |(blocks, undo, utxos, wallet_utxos, balances, validators)| {
+                Self::tx_apply((utxos, wallet_utxos, balances, validators), &changes)?;
+                undo.remove(&key)?;
+                blocks.remove(&key)?;
+                Ok(())
+            },
+        );
+
+        match result {
+            Ok(_) => self.flush().await,
+            Err(_) => Err("Reverting block problem-(tools/storage/embedded 321)"),
+        }
+    }
+
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str> {
+        match self.undo.contains_key(number.to_be_bytes()) {
+            Ok(found) => Ok(found),
+            Err(_) => Err("Reading from embedded database problem-(tools/storage/embedded 328)"),
+        }
+    }
+
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str> {
+        self.put_flushed(&self.blocks, &block.header.number.to_be_bytes(), block)
+            .await
+    }
+
+    async fn last_block(&self) -> Result<Option<Block>, &'static str> {
+        match self.blocks.last() {
+            Ok(Some((_, bytes))) => Self::decode(&bytes).map(Some),
+            Ok(None) => Ok(None),
+            Err(_) => Err("Error while finding last block-(tools/storage/embedded 341)"),
+        }
+    }
+
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str> {
+        Self::get(&self.blocks, &number.to_be_bytes())
+    }
+
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str> {
+        let mut blocks = Vec::new();
+        for item in self
+            .blocks
+            .iter()
+            .rev()
+            .skip(skip as usize)
+            .take(limit as usize)
+        {
+            match item {
+                Ok((_, bytes)) => match Self::decode(&bytes) {
+                    Ok(block) => blocks.push(block),
+                    Err(e) => return Err(e),
+                },
+                Err(_) => {
+                    return Err("Error while finding latest blocks-(tools/storage/embedded 364)")
+                }
+            }
+        }
+        Ok(blocks)
+    }
+
+    //there is no index of transactions, so blocks are scanned from the tip
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str> {
+        for item in self.blocks.iter().rev() {
+            match item {
+                Ok((_, bytes)) => {
+                    let block: Block = match Self::decode(&bytes) {
+                        Ok(block) => block,
+                        Err(e) => return Err(e),
+                    };
+                    if block.body.transactions.iter().any(|trx| trx.hash == hash) {
+                        return Ok(Some(block));
+                    }
+                }
+                Err(_) => {
+                    return Err(
+                        "Error while finding block of transaction-(tools/storage/embedded 386)",
+                    )
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str> {
+        match Self::get::<UtxoRecord>(&self.utxos, unspent_hash.as_bytes()) {
+            Ok(Some(record)) if record.wallet == *wallet => Ok(Some(record.utxo)),
+            Ok(_) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str> {
+        let mut utxos = Vec::new();
+        let prefix = format!("{}/", wallet);
+        for item in self.wallet_utxos.scan_prefix(prefix.as_bytes()) {
+            let key = match item {
+                Ok((key, _)) => key,
+                Err(_) => {
+                    return Err(
+                        "Reading from embedded database problem-(tools/storage/embedded 414)",
+                    )
+                }
+            };
+            let unspent_hash = String::from_utf8_lossy(&key[prefix.len()..]).to_string();
+            match self.find_utxo(wallet, &unspent_hash).await {
+                Ok(Some(utxo)) => utxos.push(utxo),
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(utxos)
+    }
+
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str> {
+        match Self::get(&self.balances, wallet.to_string().as_bytes()) {
+            Ok(balance) => Ok(balance.unwrap_or_default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str> {
+        Self::all(&self.validators)
+    }
+
+    async fn validators_count(&self) -> Result<u64, &'static str> {
+        Ok(self.validators.len() as u64)
+    }
+
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str> {
+        Self::get(&self.validators, peerid.to_string().as_bytes())
+    }
+
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        self.put_flushed(
+            &self.validators,
+            validator.peerid.to_string().as_bytes(),
+            validator,
+        )
+        .await
+    }
+
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        match self.find_validator(&validator.peerid).await {
+            Ok(Some(_)) => self.insert_validator(validator).await,
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str> {
+        match self.validators().await {
+            Ok(validators) => {
+                let mut count = 0;
+                for v in validators {
+                    if v.peerid == *peerid {
+                        if self.validators.remove(v.peerid.to_string()).is_err() {
+                            return Err(
+                                "Deleting validators has problem-(tools/storage/embedded 472)",
+                            );
+                        }
+                        count += 1;
+                    }
+                }
+                match self.flush().await {
+                    Ok(_) => Ok(count),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn clear_validators(&self) -> Result<(), &'static str> {
+        match self.validators.clear() {
+            Ok(_) => self.flush().await,
+            Err(_) => Err("Deleting validators has problem-(tools/storage/embedded 490)"),
+        }
+    }
+
+    async fn relay(&self) -> Result<Option<Relay>, &'static str> {
+        Self::get(&self.relay, b"relay")
+    }
+
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.put_flushed(&self.relay, b"relay", relay).await
+    }
+
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str> {
+        Self::all(&self.relays)
+    }
+
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.put_flushed(&self.relays, relay.addr.as_bytes(), relay)
+            .await
+    }
+
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str> {
+        match self.relays.remove(addr) {
+            Ok(_) => self.flush().await,
+            Err(_) => Err("random relay has problem for deleting-(tools/storage/embedded 514)"),
+        }
+    }
+
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str> {
+        Self::get(&self.finality, b"certificate")
+    }
+
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str> {
+        self.put_flushed(&self.finality, b"certificate", certificate)
+            .await
+    }
+
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str> {
+        self.put_flushed(&self.evidence, evidence.id.as_bytes(), evidence)
+            .await
+    }
+
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str> {
+        Self::get(&self.evidence, id.as_bytes())
+    }
+
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str> {
+        Self::all(&self.evidence)
+    }
+}
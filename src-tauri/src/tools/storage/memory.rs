@@ -0,0 +1,416 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use libp2p::PeerId;
+use rust_decimal::Decimal;
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{attestation::Certificate, block::Block},
+        relay::Relay,
+        validator::Validator,
+    },
+    tools::{changeset::ChangeSet, evidence::Evidence, undo::Undo, utxo::UTXO},
+};
+
+use super::Storage;
+
+//in-memory storage for running consensus and validation code without a mongod (tests and small tools)
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    data: Mutex<MemoryData>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryData {
+    blocks: Vec<Block>,
+    undos: Vec<Undo>,
+    utxos: HashMap<String, (Public, UTXO)>, //keyed by unspent hash
+    balances: HashMap<Public, Decimal>,
+    validators: Vec<Validator>,
+    relay: Option<Relay>,
+    relays: Vec<Relay>,
+    finality: Option<Certificate>,
+    evidence: Vec<Evidence>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //apply utxos and validators of the change set
+    fn apply(data: &mut MemoryData, changes: &ChangeSet) {
+        for (_, utxo) in &changes.spent {
+            data.utxos.remove(&utxo.unspent_hash);
+        }
+        for (wallet, utxo) in &changes.created {
+            data.utxos
+                .insert(utxo.unspent_hash.clone(), (*wallet, utxo.clone()));
+        }
+        for (wallet, value) in changes.balance_changes() {
+            *data.balances.entry(wallet).or_default() += value;
+        }
+        for validator in &changes.validators {
+            if let Some(v) = data
+                .validators
+                .iter_mut()
+                .find(|v| v.peerid == validator.peerid)
+            {
+                *v = validator.clone();
+            }
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    async fn clear(&self) -> Result<(), &'static str> {
+        let mut data = self.data.lock().unwrap();
+        data.blocks.clear();
+        data.undos.clear();
+        data.utxos.clear();
+        data.balances.clear();
+        data.validators.clear();
+        data.finality = None;
+        Ok(())
+    }
+
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str> {
+        //one lock for the whole change set so no one sees a half applied block
+        let mut data = self.data.lock().unwrap();
+        Self::apply(&mut data, changes);
+        if let Some(block) = block {
+            data.blocks.push(block.clone());
+            data.undos.push(Undo::new(block.header.number, changes));
+        }
+        Ok(())
+    }
+
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str> {
+        let mut data = self.data.lock().unwrap();
+        match data.undos.iter().position(|u| u.number == number) {
+            Some(i) => {
+                let undo = data.undos.remove(i);
+                Self::apply(&mut data, &undo.changes.inverse());
+                data.blocks.retain(|b| b.header.number != number);
+                Ok(())
+            }
+            None => Err("Undo record of the block not found-(tools/storage/memory 92)"),
+        }
+    }
+
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str> {
+        let data = self.data.lock().unwrap();
+        Ok(data.undos.iter().any(|u| u.number == number))
+    }
+
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str> {
+        self.data.lock().unwrap().blocks.push(block.clone());
+        Ok(())
+    }
+
+    async fn last_block(&self) -> Result<Option<Block>, &'static str> {
+        let data = self.data.lock().unwrap();
+        let last = data.blocks.iter().max_by_key(|b| b.header.number);
+        Ok(last.cloned())
+    }
+
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str> {
+        let data = self.data.lock().unwrap();
+        let block = data.blocks.iter().find(|b| b.header.number == number);
+        Ok(block.cloned())
+    }
+
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str> {
+        let data = self.data.lock().unwrap();
+        let block = data
+            .blocks
+            .iter()
+            .find(|b| b.body.transactions.iter().any(|trx| trx.hash == hash));
+        Ok(block.cloned())
+    }
+
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str> {
+        let data = self.data.lock().unwrap();
+        let mut blocks = data.blocks.clone();
+        blocks.sort_by(|a, b| b.header.number.cmp(&a.header.number));
+        Ok(blocks
+            .into_iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str> {
+        let data = self.data.lock().unwrap();
+        match data.utxos.get(unspent_hash) {
+            Some((owner, utxo)) if owner == wallet => Ok(Some(utxo.clone())),
+            _ => Ok(None),
+        }
+    }
+
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .utxos
+            .values()
+            .filter(|(owner, _)| owner == wallet)
+            .map(|(_, utxo)| utxo.clone())
+            .collect())
+    }
+
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str> {
+        let data = self.data.lock().unwrap();
+        Ok(data.balances.get(wallet).cloned().unwrap_or_default())
+    }
+
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str> {
+        Ok(self.data.lock().unwrap().validators.clone())
+    }
+
+    async fn validators_count(&self) -> Result<u64, &'static str> {
+        Ok(self.data.lock().unwrap().validators.len() as u64)
+    }
+
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .validators
+            .iter()
+            .find(|v| v.peerid == *peerid)
+            .cloned())
+    }
+
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        self.data.lock().unwrap().validators.push(validator.clone());
+        Ok(())
+    }
+
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(v) = data
+            .validators
+            .iter_mut()
+            .find(|v| v.peerid == validator.peerid)
+        {
+            *v = validator.clone();
+        }
+        Ok(())
+    }
+
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str> {
+        let mut data = self.data.lock().unwrap();
+        let before = data.validators.len();
+        data.validators.retain(|v| v.peerid != *peerid);
+        Ok((before - data.validators.len()) as u64)
+    }
+
+    async fn clear_validators(&self) -> Result<(), &'static str> {
+        self.data.lock().unwrap().validators.clear();
+        Ok(())
+    }
+
+    async fn relay(&self) -> Result<Option<Relay>, &'static str> {
+        Ok(self.data.lock().unwrap().relay.clone())
+    }
+
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.data.lock().unwrap().relay = Some(relay.clone());
+        Ok(())
+    }
+
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str> {
+        Ok(self.data.lock().unwrap().relays.clone())
+    }
+
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        self.data.lock().unwrap().relays.push(relay.clone());
+        Ok(())
+    }
+
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str> {
+        self.data.lock().unwrap().relays.retain(|r| r.addr != addr);
+        Ok(())
+    }
+
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str> {
+        Ok(self.data.lock().unwrap().finality.clone())
+    }
+
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str> {
+        self.data.lock().unwrap().finality = Some(certificate.clone());
+        Ok(())
+    }
+
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str> {
+        self.data.lock().unwrap().evidence.push(evidence.clone());
+        Ok(())
+    }
+
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str> {
+        let data = self.data.lock().unwrap();
+        Ok(data.evidence.iter().find(|e| e.id == id).cloned())
+    }
+
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str> {
+        Ok(self.data.lock().unwrap().evidence.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::PeerId;
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use sp_core::{crypto::Ss58Codec, ed25519::Public};
+
+    use crate::{
+        generator::{block::block::Block, validator::Validator},
+        tools::{changeset::ChangeSet, storage::Storage, utxo::UTXO},
+    };
+
+    use super::MemoryStorage;
+
+    fn wallet(byte: u8) -> Public {
+        Public::from_raw([byte; 32])
+    }
+
+    fn utxo(hash: &str, value: i64) -> UTXO {
+        UTXO {
+            block: 1,
+            trx_hash: "trx".to_string(),
+            output_hash: "output".to_string(),
+            unspent_hash: hash.to_string(),
+            unspent: Decimal::from(value),
+        }
+    }
+
+    fn block(number: u64) -> Block {
+        let peerid = PeerId::random().to_string();
+        serde_json::from_value(json!({
+            "header": {
+                "version": 3,
+                "number": number,
+                "hash": format!("{:064x}", number),
+                "previous": format!("{:064x}", number - 1),
+                "validator": peerid,
+                "relay": peerid,
+                "merkel": "",
+                "body_hash": "",
+                "signature": {"signatgure": "00".repeat(64), "key": wallet(9).to_ss58check()},
+                "date": "2024-01-02 03:04:05 UTC"
+            },
+            "body": {
+                "coinbase": {
+                    "hash": "",
+                    "size": 0,
+                    "merkel": "",
+                    "reward": "0",
+                    "output": {"hash": "", "number": 0, "unspents": []},
+                    "fees": "0",
+                    "relay_fee": "0",
+                    "validator_fee": "0"
+                },
+                "transactions": []
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn revert_restores_utxos_and_balances() {
+        let db = MemoryStorage::new();
+        let (alice, bob) = (wallet(1), wallet(2));
+
+        let mut first = ChangeSet::new();
+        first.create(&alice, utxo("a", 10));
+        db.commit(Some(&block(1)), &first).await.unwrap();
+
+        let mut second = ChangeSet::new();
+        second.spend(&alice, &utxo("a", 10));
+        second.create(&bob, utxo("b", 4));
+        second.create(&alice, utxo("c", 6));
+        db.commit(Some(&block(2)), &second).await.unwrap();
+        assert_eq!(db.balance(&alice).await.unwrap(), Decimal::from(6));
+        assert_eq!(db.balance(&bob).await.unwrap(), Decimal::from(4));
+        assert!(db.find_utxo(&alice, "a").await.unwrap().is_none());
+
+        db.revert_block(2).await.unwrap();
+        assert_eq!(db.balance(&alice).await.unwrap(), Decimal::from(10));
+        assert_eq!(db.balance(&bob).await.unwrap(), Decimal::ZERO);
+        assert!(db.find_utxo(&alice, "a").await.unwrap().is_some());
+        assert!(db.find_utxo(&bob, "b").await.unwrap().is_none());
+        assert_eq!(db.last_block().await.unwrap().unwrap().header.number, 1);
+    }
+
+    #[tokio::test]
+    async fn revert_restores_validators() {
+        let db = MemoryStorage::new();
+        let previous = Validator {
+            peerid: PeerId::random(),
+            relay: PeerId::random(),
+            wallet: wallet(1),
+            waiting: 0,
+            paused: false,
+        };
+        db.insert_validator(&previous).await.unwrap();
+
+        let mut validator = previous.clone();
+        validator.waiting = 3;
+        let mut changes = ChangeSet::new();
+        changes.update_validator(&previous, validator);
+        db.commit(Some(&block(1)), &changes).await.unwrap();
+        let found = db.find_validator(&previous.peerid).await.unwrap().unwrap();
+        assert_eq!(found.waiting, 3);
+
+        db.revert_block(1).await.unwrap();
+        let found = db.find_validator(&previous.peerid).await.unwrap().unwrap();
+        assert_eq!(found.waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn block_without_undo_record_is_not_reverted() {
+        let db = MemoryStorage::new();
+        db.insert_block(&block(1)).await.unwrap();
+        assert!(!db.has_undo(1).await.unwrap());
+        assert!(db.revert_block(1).await.is_err());
+        assert!(db.find_block(1).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn validators_of_a_relay_are_not_removed_by_its_peerid() {
+        let db = MemoryStorage::new();
+        let validator = Validator {
+            peerid: PeerId::random(),
+            relay: PeerId::random(),
+            wallet: wallet(1),
+            waiting: 0,
+            paused: false,
+        };
+        db.insert_validator(&validator).await.unwrap();
+
+        assert_eq!(db.remove_validators(&validator.relay).await.unwrap(), 0);
+        assert_eq!(db.remove_validators(&validator.peerid).await.unwrap(), 1);
+        assert!(db.validators().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn latest_blocks_are_sorted_descending() {
+        let db = MemoryStorage::new();
+        for number in [2, 1, 3] {
+            db.insert_block(&block(number)).await.unwrap();
+        }
+        let numbers: Vec<u64> = db
+            .latest_blocks(1, 2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|b| b.header.number)
+            .collect();
+        assert_eq!(numbers, vec![2, 1]);
+    }
+}
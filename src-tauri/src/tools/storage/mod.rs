@@ -0,0 +1,80 @@
+use libp2p::PeerId;
+use rust_decimal::Decimal;
+use sp_core::ed25519::Public;
+
+use crate::generator::{
+    block::{attestation::Certificate, block::Block},
+    relay::Relay,
+    validator::Validator,
+};
+
+use super::{changeset::ChangeSet, evidence::Evidence, utxo::UTXO};
+
+pub mod cached;
+pub mod embedded;
+#[cfg(any(test, feature = "memory-storage"))] //in-memory backend for tests and small tools
+pub mod memory;
+pub mod mongo;
+pub mod store;
+
+// Storage is the only way subsystems reach the chain state (blocks, UTXO sets, validators and relays)
+// so consensus and validation code can run against MongoDB or against an in-memory backend in tests and small tools
+#[allow(async_fn_in_trait)] //backends are concrete types, so callers get Send futures where they need them
+pub trait Storage {
+    //remove chain state (blocks, undo records, utxos, validators and finality) for importing a full snapshot, relays are kept
+    async fn clear(&self) -> Result<(), &'static str>;
+
+    //apply staged utxo spends, new utxos and validators' waiting and then insert the block (if there is) all together
+    //the undo record of the block is written with it
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str>;
+    //undo changes of the block by its undo record and remove the block and the record
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str>;
+    //write changes that a cache keeps in memory into the storage (backends write them in commit)
+    async fn flush(&self) -> Result<(), &'static str> {
+        Ok(())
+    }
+    //check that the block has an undo record (blocks of a snapshot don't have)
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str>;
+
+    //blocks
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str>;
+    async fn last_block(&self) -> Result<Option<Block>, &'static str>;
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str>;
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str>; //sorted by number descending
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str>; //block that includes the transaction
+
+    //utxo set keyed by unspent hash with an index of wallets and a balance per wallet
+    //they only change by commit
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str>;
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str>;
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str>;
+
+    //validators registry
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str>;
+    async fn validators_count(&self) -> Result<u64, &'static str>;
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str>;
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str>;
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str>; //replace by peerid
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str>; //remove the validator of the peerid
+    async fn clear_validators(&self) -> Result<(), &'static str>;
+
+    //connected relay and known relays addresses
+    async fn relay(&self) -> Result<Option<Relay>, &'static str>;
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str>;
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str>;
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str>;
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str>;
+
+    //finality certificate of the last final block
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str>;
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str>;
+
+    //evidence of wrongdoers (it is kept when chain state is cleared)
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str>;
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str>;
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str>;
+}
@@ -0,0 +1,722 @@
+use libp2p::{futures::StreamExt, PeerId};
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, to_document, Document},
+    options::{FindOneOptions, FindOptions, ReplaceOptions},
+    Collection, Database, IndexModel,
+};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{attestation::Certificate, block::Block},
+        relay::Relay,
+        validator::Validator,
+    },
+    tools::{
+        changeset::ChangeSet,
+        evidence::Evidence,
+        undo::Undo,
+        utxo::{Person, UTXO},
+    },
+};
+
+use super::Storage;
+
+//collections of Centichain database
+const BLOCKS: &str = "Blocks";
+const UNDO: &str = "undo";
+const UTXOS: &str = "utxoset"; //one document per utxo that its _id is unspent hash
+const BALANCES: &str = "balances"; //one document per wallet that its _id is wallet
+const LEGACY_UTXOS: &str = "UTXOs"; //one document per wallet (it was replaced by utxoset)
+const VALIDATORS: &str = "validators";
+const RELAY: &str = "relay";
+const RELAYS: &str = "relays";
+const FINALITY: &str = "finality"; //one document that is the certificate of the last final block
+const EVIDENCE: &str = "evidence";
+const JOURNAL: &str = "journal"; //one document that is the commit or revert in progress
+
+// Commit or revert that is written into the journal before its changes
+#[derive(Debug, Serialize, Deserialize)]
+enum Pending {
+    Commit {
+        block: Option<Block>,
+        changes: ChangeSet,
+    },
+    Revert {
+        number: u64,
+        changes: ChangeSet,
+    },
+}
+
+// Document of the journal, its id is written into balances that were changed by it
+// so a retry doesn't add the changes to a balance twice
+#[derive(Debug, Serialize, Deserialize)]
+struct Journal {
+    id: String,
+    pending: Pending,
+}
+
+impl Storage for Database {
+    async fn clear(&self) -> Result<(), &'static str> {
+        for name in [
+            BLOCKS,
+            UNDO,
+            UTXOS,
+            BALANCES,
+            LEGACY_UTXOS,
+            VALIDATORS,
+            FINALITY,
+            JOURNAL,
+        ] {
+            if self.collection::<Document>(name).drop().await.is_err() {
+                return Err("Drop collections error-(tools/storage/mongo 74)");
+            }
+        }
+        Ok(())
+    }
+
+    //multi-document transactions need a replica set, so the commit is written into the journal first
+    //and then every write is idempotent (see roll_forward), a failed or interrupted commit is completed
+    //by the next commit or when the database is connected again
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str> {
+        let pending = Pending::Commit {
+            block: block.cloned(),
+            changes: changes.clone(),
+        };
+        journal(self, pending).await
+    }
+
+    //revert of a block is journaled like commit so it is never left half done
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str> {
+        let collection: Collection<Document> = self.collection(UNDO);
+        match collection.find_one(doc! {"number": number as i64}).await {
+            Ok(Some(doc)) => match from_document::<Undo>(doc) {
+                Ok(undo) => {
+                    let pending = Pending::Revert {
+                        number,
+                        changes: undo.changes.inverse(),
+                    };
+                    journal(self, pending).await
+                }
+                Err(_) => Err("Undo record of the block is corrupt-(tools/storage/mongo 103)"),
+            },
+            Ok(None) => Err("Undo record of the block not found-(tools/storage/mongo 105)"),
+            Err(_) => Err("Error while finding undo record-(tools/storage/mongo 106)"),
+        }
+    }
+
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str> {
+        let collection: Collection<Document> = self.collection(UNDO);
+        match collection
+            .count_documents(doc! {"number": number as i64})
+            .await
+        {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err("Error while finding undo record-(tools/storage/mongo 117)"),
+        }
+    }
+
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str> {
+        let document = match encode(block) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(BLOCKS);
+        match collection.insert_one(document).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error during insertion of block-(tools/storage/mongo 129)"),
+        }
+    }
+
+    async fn last_block(&self) -> Result<Option<Block>, &'static str> {
+        let collection: Collection<Document> = self.collection(BLOCKS);
+        let options = FindOneOptions::builder()
+            .sort(doc! {"header.number": -1})
+            .build();
+        match collection.find_one(doc! {}).with_options(options).await {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Error while finding last block-(tools/storage/mongo 140)"),
+        }
+    }
+
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str> {
+        let collection: Collection<Document> = self.collection(BLOCKS);
+        match collection
+            .find_one(doc! {"header.number": number as i64})
+            .await
+        {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Error while finding block-(tools/storage/mongo 151)"),
+        }
+    }
+
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str> {
+        let collection: Collection<Document> = self.collection(BLOCKS);
+        let options = FindOptions::builder()
+            .sort(doc! {"header.number": -1})
+            .skip(skip)
+            .limit(limit as i64)
+            .build();
+        match collection.find(doc! {}).with_options(options).await {
+            Ok(mut cursor) => {
+                let mut blocks = Vec::new();
+                while let Some(Ok(doc)) = cursor.next().await {
+                    match decode(doc) {
+                        Ok(value) => blocks.push(value),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(blocks)
+            }
+            Err(_) => Err("Error while finding latest blocks-(tools/storage/mongo 173)"),
+        }
+    }
+
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str> {
+        let collection: Collection<Document> = self.collection(BLOCKS);
+        match collection
+            .find_one(doc! {"body.transactions.hash": hash})
+            .await
+        {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Error while finding block of transaction-(tools/storage/mongo 184)"),
+        }
+    }
+
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str> {
+        let collection: Collection<Document> = self.collection(UTXOS);
+        let filter = doc! {"_id": unspent_hash, "wallet": wallet.to_string()};
+        match collection.find_one(filter).await {
+            Ok(opt) => opt.map(decode_utxo).transpose(),
+            Err(_) => Err("Problem from query of utxos collection-(tools/storage/mongo 197)"),
+        }
+    }
+
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str> {
+        let collection: Collection<Document> = self.collection(UTXOS);
+        match collection.find(doc! {"wallet": wallet.to_string()}).await {
+            Ok(mut cursor) => {
+                let mut utxos = Vec::new();
+                while let Some(Ok(doc)) = cursor.next().await {
+                    match decode_utxo(doc) {
+                        Ok(utxo) => utxos.push(utxo),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(utxos)
+            }
+            Err(_) => Err("Problem from query of utxos collection-(tools/storage/mongo 214)"),
+        }
+    }
+
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str> {
+        let collection: Collection<Document> = self.collection(BALANCES);
+        match collection.find_one(doc! {"_id": wallet.to_string()}).await {
+            Ok(Some(doc)) => match doc.get_str("balance").ok().and_then(|b| b.parse().ok()) {
+                Some(balance) => Ok(balance),
+                None => Err("Balance of the wallet is corrupt-(tools/storage/mongo 223)"),
+            },
+            Ok(None) => Ok(Decimal::ZERO),
+            Err(_) => Err("Problem from query of balances collection-(tools/storage/mongo 226)"),
+        }
+    }
+
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str> {
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection.find(doc! {}).await {
+            Ok(mut cursor) => {
+                let mut validators = Vec::new();
+                while let Some(Ok(doc)) = cursor.next().await {
+                    match decode(doc) {
+                        Ok(value) => validators.push(value),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(validators)
+            }
+            Err(_) => Err("Error during quering validators-(tools/storage/mongo 243)"),
+        }
+    }
+
+    async fn validators_count(&self) -> Result<u64, &'static str> {
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection.count_documents(doc! {}).await {
+            Ok(count) => Ok(count),
+            Err(_) => Err("Error while get count of validators-(tools/storage/mongo 251)"),
+        }
+    }
+
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str> {
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection
+            .find_one(doc! {"peerid": peerid.to_string()})
+            .await
+        {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Error while finding validator-(tools/storage/mongo 262)"),
+        }
+    }
+
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        let document = match encode(validator) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection.insert_one(document).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error while inserting new validator-(tools/storage/mongo 274)"),
+        }
+    }
+
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        let document = match encode(validator) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        let filter = doc! {"peerid": validator.peerid.to_string()};
+        match collection.replace_one(filter, document).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error during the replacing of validator-(tools/storage/mongo 287)"),
+        }
+    }
+
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str> {
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection
+            .delete_many(doc! {"peerid": peerid.to_string()})
+            .await
+        {
+            Ok(result) => Ok(result.deleted_count),
+            Err(_) => Err("Deleting validators from mongodb has problem-(tools/storage/mongo 298)"),
+        }
+    }
+
+    async fn clear_validators(&self) -> Result<(), &'static str> {
+        let collection: Collection<Document> = self.collection(VALIDATORS);
+        match collection.delete_many(doc! {}).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Deleting validators from mongodb has problem-(tools/storage/mongo 306)"),
+        }
+    }
+
+    async fn relay(&self) -> Result<Option<Relay>, &'static str> {
+        let collection: Collection<Document> = self.collection(RELAY);
+        match collection.find_one(doc! {}).await {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Qurying relay problem! please check your mongodb."),
+        }
+    }
+
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        let document = match encode(relay) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(RELAY);
+        match collection.delete_many(doc! {}).await {
+            Ok(_) => match collection.insert_one(document).await {
+                Ok(_) => Ok(()),
+                Err(_) => Err("error from insert of relay into mongodb-(tools/storage/mongo 327)"),
+            },
+            Err(_) => Err("Deleting connected relays problem-(tools/storage/mongo 329)"),
+        }
+    }
+
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str> {
+        let collection: Collection<Document> = self.collection(RELAYS);
+        match collection.find(doc! {}).await {
+            Ok(mut cursor) => {
+                let mut relays = Vec::new();
+                while let Some(Ok(doc)) = cursor.next().await {
+                    match decode(doc) {
+                        Ok(value) => relays.push(value),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(relays)
+            }
+            Err(_) => Err("problem in quering relays-(tools/storage/mongo 346)"),
+        }
+    }
+
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        let document = match encode(relay) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(RELAYS);
+        match collection.insert_one(document).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error while inserting relay-(tools/storage/mongo 358)"),
+        }
+    }
+
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str> {
+        let collection: Collection<Document> = self.collection(RELAYS);
+        match collection.delete_one(doc! {"addr": addr}).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("random relay has problem for deleting-(tools/storage/mongo 366)"),
+        }
+    }
+
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str> {
+        let collection: Collection<Document> = self.collection(FINALITY);
+        match collection.find_one(doc! {}).await {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Qurying finality problem-(tools/storage/mongo 374)"),
+        }
+    }
+
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str> {
+        let document = match encode(certificate) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(FINALITY);
+        match collection.delete_many(doc! {}).await {
+            Ok(_) => match collection.insert_one(document).await {
+                Ok(_) => Ok(()),
+                Err(_) => {
+                    Err("Error while inserting finality certificate-(tools/storage/mongo 388)")
+                }
+            },
+            Err(_) => Err("Deleting former finality certificate problem-(tools/storage/mongo 391)"),
+        }
+    }
+
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str> {
+        let document = match encode(evidence) {
+            Ok(document) => document,
+            Err(e) => return Err(e),
+        };
+        let collection: Collection<Document> = self.collection(EVIDENCE);
+        match collection.insert_one(document).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error while inserting evidence-(tools/storage/mongo 403)"),
+        }
+    }
+
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str> {
+        let collection: Collection<Document> = self.collection(EVIDENCE);
+        match collection.find_one(doc! {"id": id}).await {
+            Ok(opt) => opt.map(decode).transpose(),
+            Err(_) => Err("Qurying evidence problem-(tools/storage/mongo 411)"),
+        }
+    }
+
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str> {
+        let collection: Collection<Document> = self.collection(EVIDENCE);
+        match collection.find(doc! {}).await {
+            Ok(mut cursor) => {
+                let mut evidence = Vec::new();
+                while let Some(Ok(doc)) = cursor.next().await {
+                    match decode(doc) {
+                        Ok(value) => evidence.push(value),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(evidence)
+            }
+            Err(_) => Err("problem in quering evidence-(tools/storage/mongo 428)"),
+        }
+    }
+}
+
+//write the journal, apply it and then remove it
+//a journal of a former failed commit is completed first, so changes are applied in their order
+async fn journal(db: &Database, pending: Pending) -> Result<(), &'static str> {
+    if let Err(e) = recover(db).await {
+        return Err(e);
+    }
+    let collection: Collection<Document> = db.collection(JOURNAL);
+    let journal = Journal {
+        id: ObjectId::new().to_hex(),
+        pending,
+    };
+    let document = match to_document(&journal) {
+        Ok(document) => document,
+        Err(_) => return Err("Error while encoding journal-(tools/storage/mongo 446)"),
+    };
+    match collection.insert_one(document).await {
+        Ok(_) => match roll_forward(db, Some(&journal.id), &journal.pending).await {
+            Ok(_) => match collection.delete_many(doc! {}).await {
+                Ok(_) => Ok(()),
+                Err(_) => Err("Error while removing journal-(tools/storage/mongo 452)"),
+            },
+            Err(e) => Err(e),
+        },
+        Err(_) => Err("Error while writing journal-(tools/storage/mongo 456)"),
+    }
+}
+
+//complete the commit or revert that is in the journal (if there is)
+//journal of former versions doesn't have an id, so balances of its wallets are recounted
+pub async fn recover<'a>(db: &Database) -> Result<(), &'a str> {
+    let collection: Collection<Document> = db.collection(JOURNAL);
+    let rolled = match collection.find_one(doc! {}).await {
+        Ok(Some(doc)) => match from_document::<Journal>(doc.clone()) {
+            Ok(journal) => roll_forward(db, Some(&journal.id), &journal.pending).await,
+            Err(_) => match from_document::<Pending>(doc) {
+                Ok(pending) => roll_forward(db, None, &pending).await,
+                Err(_) => Err("Journal of database is corrupt-(tools/storage/mongo 469)"),
+            },
+        },
+        Ok(None) => return Ok(()),
+        Err(_) => Err("Error while reading journal-(tools/storage/mongo 473)"),
+    };
+    match rolled {
+        Ok(_) => match collection.delete_many(doc! {}).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error while removing journal-(tools/storage/mongo 478)"),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+//every write is idempotent, so running it again after a failure gives the same state:
+//utxos are deleted or upserted by unspent hash, balances are changed once per journal id,
+//validators are replaced and undo records and blocks are upserted or deleted by number
+async fn roll_forward(
+    db: &Database,
+    id: Option<&str>,
+    pending: &Pending,
+) -> Result<(), &'static str> {
+    match pending {
+        Pending::Commit { block, changes } => {
+            if let Err(e) = apply(db, id, changes).await {
+                return Err(e);
+            }
+            match block {
+                Some(block) => {
+                    let number = block.header.number as i64;
+                    let undo = Undo::new(block.header.number, changes);
+                    match upsert(db, UNDO, doc! {"number": number}, &undo).await {
+                        Ok(_) => upsert(db, BLOCKS, doc! {"header.number": number}, block).await,
+                        Err(e) => Err(e),
+                    }
+                }
+                None => Ok(()),
+            }
+        }
+        //block is removed before undoing its changes and its undo record is removed last
+        Pending::Revert { number, changes } => {
+            let number = *number as i64;
+            let blocks: Collection<Document> = db.collection(BLOCKS);
+            if blocks
+                .delete_one(doc! {"header.number": number})
+                .await
+                .is_err()
+            {
+                return Err("Error during deleting block-(tools/storage/mongo 518)");
+            }
+            if let Err(e) = apply(db, id, changes).await {
+                return Err(e);
+            }
+            let undo: Collection<Document> = db.collection(UNDO);
+            match undo.delete_one(doc! {"number": number}).await {
+                Ok(_) => Ok(()),
+                Err(_) => Err("Error during deleting undo record-(tools/storage/mongo 526)"),
+            }
+        }
+    }
+}
+
+async fn upsert<T: Serialize>(
+    db: &Database,
+    name: &str,
+    filter: Document,
+    value: &T,
+) -> Result<(), &'static str> {
+    let collection: Collection<Document> = db.collection(name);
+    let options = ReplaceOptions::builder().upsert(true).build();
+    match to_document(value) {
+        Ok(document) => match collection
+            .replace_one(filter, document)
+            .with_options(options)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Error while writing into database-(tools/storage/mongo 547)"),
+        },
+        Err(_) => Err("Error while encoding document-(tools/storage/mongo 549)"),
+    }
+}
+
+//apply utxos, balances and validators of the change set
+//without a journal id balances of the changed wallets are recounted from the utxo set
+async fn apply(db: &Database, id: Option<&str>, changes: &ChangeSet) -> Result<(), &'static str> {
+    let utxos: Collection<Document> = db.collection(UTXOS);
+    for (_, utxo) in &changes.spent {
+        if utxos
+            .delete_one(doc! {"_id": &utxo.unspent_hash})
+            .await
+            .is_err()
+        {
+            return Err("Error during the spending of utxo-(tools/storage/mongo 563)");
+        }
+    }
+    for (wallet, utxo) in &changes.created {
+        if let Err(e) = upsert_utxo(db, wallet, utxo).await {
+            return Err(e);
+        }
+    }
+    for (wallet, value) in changes.balance_changes() {
+        let balanced = match id {
+            Some(id) => add_balance(db, &wallet, value, id).await,
+            None => recount(db, &wallet).await,
+        };
+        if let Err(e) = balanced {
+            return Err(e);
+        }
+    }
+    for validator in &changes.validators {
+        if let Err(e) = db.update_validator(validator).await {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+async fn upsert_utxo(db: &Database, wallet: &Public, utxo: &UTXO) -> Result<(), &'static str> {
+    let document = match encode(utxo) {
+        Ok(encoded) => doc! {
+            "_id": &utxo.unspent_hash,
+            "wallet": wallet.to_string(),
+            "utxo": encoded,
+        },
+        Err(e) => return Err(e),
+    };
+    let utxos: Collection<Document> = db.collection(UTXOS);
+    let options = ReplaceOptions::builder().upsert(true).build();
+    match utxos
+        .replace_one(doc! {"_id": &utxo.unspent_hash}, document)
+        .with_options(options)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Error while inserting utxo-(tools/storage/mongo 605)"),
+    }
+}
+
+//add the change to the balance of the wallet unless the balance was already changed by the journal
+async fn add_balance(
+    db: &Database,
+    wallet: &Public,
+    value: Decimal,
+    id: &str,
+) -> Result<(), &'static str> {
+    let balances: Collection<Document> = db.collection(BALANCES);
+    let balance = match balances.find_one(doc! {"_id": wallet.to_string()}).await {
+        Ok(Some(doc)) if doc.get_str("applied") == Ok(id) => return Ok(()),
+        Ok(Some(doc)) => match doc.get_str("balance").ok().and_then(|b| b.parse().ok()) {
+            Some(balance) => balance,
+            None => return Err("Balance of the wallet is corrupt-(tools/storage/mongo 621)"),
+        },
+        Ok(None) => Decimal::ZERO,
+        Err(_) => {
+            return Err("Problem from query of balances collection-(tools/storage/mongo 625)")
+        }
+    };
+    set_balance(db, wallet, balance + value, Some(id)).await
+}
+
+//balance is the sum of utxos of the wallet
+async fn recount(db: &Database, wallet: &Public) -> Result<(), &'static str> {
+    match db.wallet_utxos(wallet).await {
+        Ok(wallet_utxos) => {
+            let balance: Decimal = wallet_utxos.iter().map(|utxo| utxo.unspent).sum();
+            set_balance(db, wallet, balance, None).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn set_balance(
+    db: &Database,
+    wallet: &Public,
+    balance: Decimal,
+    id: Option<&str>,
+) -> Result<(), &'static str> {
+    let balances: Collection<Document> = db.collection(BALANCES);
+    let document = doc! {"_id": wallet.to_string(), "balance": balance.to_string(), "applied": id};
+    let options = ReplaceOptions::builder().upsert(true).build();
+    match balances
+        .replace_one(doc! {"_id": wallet.to_string()}, document)
+        .with_options(options)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Error while updating balance-(tools/storage/mongo 657)"),
+    }
+}
+
+//utxos of former versions are one document per wallet (Person), they are moved into the utxo set
+//every wallet is moved by itself and its balance is recounted, so an interrupted migration is completed by the next one
+pub async fn migrate<'a>(db: &Database) -> Result<(), &'a str> {
+    let legacy: Collection<Document> = db.collection(LEGACY_UTXOS);
+    match legacy.find(doc! {}).await {
+        Ok(mut cursor) => {
+            while let Some(Ok(doc)) = cursor.next().await {
+                let id = doc.get("_id").cloned();
+                let person: Person = match decode(doc) {
+                    Ok(person) => person,
+                    Err(e) => return Err(e),
+                };
+                for utxo in &person.utxos {
+                    if let Err(e) = upsert_utxo(db, &person.wallet, utxo).await {
+                        return Err(e);
+                    }
+                }
+                if let Err(e) = recount(db, &person.wallet).await {
+                    return Err(e);
+                }
+                if legacy.delete_one(doc! {"_id": id}).await.is_err() {
+                    return Err("Error while removing migrated utxos-(tools/storage/mongo 682)");
+                }
+            }
+            Ok(())
+        }
+        Err(_) => Err("Problem from query of former utxos collection-(tools/storage/mongo 687)"),
+    }
+}
+
+//wallet index of utxo set (unspent hash is _id so it has its index)
+pub async fn create_indexes<'a>(db: &Database) -> Result<(), &'a str> {
+    let collection: Collection<Document> = db.collection(UTXOS);
+    let index = IndexModel::builder().keys(doc! {"wallet": 1}).build();
+    match collection.create_index(index).await {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Creating indexes of database problem-(tools/storage/mongo 697)"),
+    }
+}
+
+//decode a document of the database, a corrupt document is returned as an error
+fn decode<T: DeserializeOwned>(doc: Document) -> Result<T, &'static str> {
+    match from_document(doc) {
+        Ok(value) => Ok(value),
+        Err(_) => Err("Corrupt document in database-(tools/storage/mongo 705)"),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Document, &'static str> {
+    match to_document(value) {
+        Ok(document) => Ok(document),
+        Err(_) => Err("Error while encoding document-(tools/storage/mongo 712)"),
+    }
+}
+
+//utxo of a document of the utxo set
+fn decode_utxo(doc: Document) -> Result<UTXO, &'static str> {
+    match doc.get_document("utxo") {
+        Ok(utxo) => decode(utxo.clone()),
+        Err(_) => Err("Corrupt utxo in database-(tools/storage/mongo 720)"),
+    }
+}
@@ -0,0 +1,231 @@
+use libp2p::PeerId;
+use mongodb::Database;
+use rust_decimal::Decimal;
+use sp_core::ed25519::Public;
+
+use crate::{
+    generator::{
+        block::{attestation::Certificate, block::Block},
+        relay::Relay,
+        validator::Validator,
+    },
+    tools::{changeset::ChangeSet, evidence::Evidence, utxo::UTXO},
+};
+
+use super::{embedded::EmbeddedStorage, Storage};
+
+//storage that is selected in settings (connected mongodb or embedded database in data directory)
+#[derive(Debug, Clone)]
+pub enum Store {
+    MongoDB(Database),
+    Embedded(EmbeddedStorage),
+}
+
+impl Storage for Store {
+    async fn clear(&self) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.clear().await,
+            Self::Embedded(db) => db.clear().await,
+        }
+    }
+
+    async fn commit(&self, block: Option<&Block>, changes: &ChangeSet) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.commit(block, changes).await,
+            Self::Embedded(db) => db.commit(block, changes).await,
+        }
+    }
+
+    async fn revert_block(&self, number: u64) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.revert_block(number).await,
+            Self::Embedded(db) => db.revert_block(number).await,
+        }
+    }
+
+    async fn has_undo(&self, number: u64) -> Result<bool, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.has_undo(number).await,
+            Self::Embedded(db) => db.has_undo(number).await,
+        }
+    }
+
+    async fn insert_block(&self, block: &Block) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.insert_block(block).await,
+            Self::Embedded(db) => db.insert_block(block).await,
+        }
+    }
+
+    async fn last_block(&self) -> Result<Option<Block>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.last_block().await,
+            Self::Embedded(db) => db.last_block().await,
+        }
+    }
+
+    async fn find_block(&self, number: u64) -> Result<Option<Block>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.find_block(number).await,
+            Self::Embedded(db) => db.find_block(number).await,
+        }
+    }
+
+    async fn latest_blocks(&self, skip: u64, limit: u64) -> Result<Vec<Block>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.latest_blocks(skip, limit).await,
+            Self::Embedded(db) => db.latest_blocks(skip, limit).await,
+        }
+    }
+
+    async fn transaction_block(&self, hash: &str) -> Result<Option<Block>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.transaction_block(hash).await,
+            Self::Embedded(db) => db.transaction_block(hash).await,
+        }
+    }
+
+    async fn find_utxo(
+        &self,
+        wallet: &Public,
+        unspent_hash: &str,
+    ) -> Result<Option<UTXO>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.find_utxo(wallet, unspent_hash).await,
+            Self::Embedded(db) => db.find_utxo(wallet, unspent_hash).await,
+        }
+    }
+
+    async fn wallet_utxos(&self, wallet: &Public) -> Result<Vec<UTXO>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.wallet_utxos(wallet).await,
+            Self::Embedded(db) => db.wallet_utxos(wallet).await,
+        }
+    }
+
+    async fn balance(&self, wallet: &Public) -> Result<Decimal, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.balance(wallet).await,
+            Self::Embedded(db) => db.balance(wallet).await,
+        }
+    }
+
+    async fn validators(&self) -> Result<Vec<Validator>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.validators().await,
+            Self::Embedded(db) => db.validators().await,
+        }
+    }
+
+    async fn validators_count(&self) -> Result<u64, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.validators_count().await,
+            Self::Embedded(db) => db.validators_count().await,
+        }
+    }
+
+    async fn find_validator(&self, peerid: &PeerId) -> Result<Option<Validator>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.find_validator(peerid).await,
+            Self::Embedded(db) => db.find_validator(peerid).await,
+        }
+    }
+
+    async fn insert_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.insert_validator(validator).await,
+            Self::Embedded(db) => db.insert_validator(validator).await,
+        }
+    }
+
+    async fn update_validator(&self, validator: &Validator) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.update_validator(validator).await,
+            Self::Embedded(db) => db.update_validator(validator).await,
+        }
+    }
+
+    async fn remove_validators(&self, peerid: &PeerId) -> Result<u64, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.remove_validators(peerid).await,
+            Self::Embedded(db) => db.remove_validators(peerid).await,
+        }
+    }
+
+    async fn clear_validators(&self) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.clear_validators().await,
+            Self::Embedded(db) => db.clear_validators().await,
+        }
+    }
+
+    async fn relay(&self) -> Result<Option<Relay>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.relay().await,
+            Self::Embedded(db) => db.relay().await,
+        }
+    }
+
+    async fn set_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.set_relay(relay).await,
+            Self::Embedded(db) => db.set_relay(relay).await,
+        }
+    }
+
+    async fn relays(&self) -> Result<Vec<Relay>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.relays().await,
+            Self::Embedded(db) => db.relays().await,
+        }
+    }
+
+    async fn insert_relay(&self, relay: &Relay) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.insert_relay(relay).await,
+            Self::Embedded(db) => db.insert_relay(relay).await,
+        }
+    }
+
+    async fn remove_relay(&self, addr: &str) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.remove_relay(addr).await,
+            Self::Embedded(db) => db.remove_relay(addr).await,
+        }
+    }
+
+    async fn finality(&self) -> Result<Option<Certificate>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.finality().await,
+            Self::Embedded(db) => db.finality().await,
+        }
+    }
+
+    async fn set_finality(&self, certificate: &Certificate) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.set_finality(certificate).await,
+            Self::Embedded(db) => db.set_finality(certificate).await,
+        }
+    }
+
+    async fn insert_evidence(&self, evidence: &Evidence) -> Result<(), &'static str> {
+        match self {
+            Self::MongoDB(db) => db.insert_evidence(evidence).await,
+            Self::Embedded(db) => db.insert_evidence(evidence).await,
+        }
+    }
+
+    async fn find_evidence(&self, id: &str) -> Result<Option<Evidence>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.find_evidence(id).await,
+            Self::Embedded(db) => db.find_evidence(id).await,
+        }
+    }
+
+    async fn evidence(&self) -> Result<Vec<Evidence>, &'static str> {
+        match self {
+            Self::MongoDB(db) => db.evidence().await,
+            Self::Embedded(db) => db.evidence().await,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use super::{changeset::ChangeSet, storage::Storage};
+
+// Undo record of a block that is written with the block in commit
+// it includes spent utxos, created utxos and waiting of validators before the block
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Undo {
+    pub number: u64,
+    pub changes: ChangeSet,
+}
+
+impl Undo {
+    pub fn new(number: u64, changes: &ChangeSet) -> Self {
+        Self {
+            number,
+            changes: changes.clone(),
+        }
+    }
+
+    // Rewind blocks, utxos and validators to the block of the height and return count of reverted blocks
+    // blocks are reverted from the tip one by one, so utxos of later blocks are undone first
+    pub async fn revert_to<'a, S: Storage>(db: &'a S, height: u64) -> Result<u64, &'a str> {
+        let mut reverted = 0;
+        loop {
+            match db.last_block().await {
+                Ok(Some(block)) if block.header.number > height => {
+                    match db.revert_block(block.header.number).await {
+                        Ok(_) => reverted += 1,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(_) => return Ok(reverted),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
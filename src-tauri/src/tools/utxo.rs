@@ -1,28 +1,20 @@
-use mongodb::{
-    bson::{doc, from_document, to_document, Document},
-    Collection, Database,
-};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use sp_core::ed25519::Public;
-use tauri::Emitter;
 
-use crate::{events::gossip_messages::transactions::Transactions, generator::transaction::Unspent};
+use crate::generator::{encoding::Canonical, transaction::Unspent};
+
+use super::{changeset::ChangeSet, invalid::Invalid, storage::Storage};
 
 // Represents a person with their wallet and UTXOs
-#[derive(Debug, Serialize, Deserialize)]
+// it is the document of UTXOs collection in dumps of the network (storage keeps utxos by unspent hash)
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Person {
     pub wallet: Public,
     pub utxos: Vec<UTXO>,
 }
 
-impl Person {
-    fn new(wallet: Public, utxos: Vec<UTXO>) -> Self {
-        Self { wallet, utxos }
-    }
-}
-
 // Define a UTXO model that includes the transaction hash, the unspent value,
 // the output hash of its transaction, and the block number of the transaction
 #[serde_as]
@@ -36,58 +28,44 @@ pub struct UTXO {
     pub unspent: Decimal,
 }
 
-impl UTXO {
-    // Check if a UTXO exists and remove it if it does
-    pub async fn check<'a>(&self, db: &Database, wallet: &Public) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("UTXOs");
-        let filter = doc! {"wallet": wallet.to_string()};
-        let query = collection.find_one(filter).await;
-        match query {
-            Ok(opt) => match opt {
-                Some(doc) => {
-                    // Convert document to person structure
-                    let mut person: Person = from_document(doc).unwrap();
-                    // Find index of UTXO in the person's UTXOs
-                    let index = person
-                        .utxos
-                        .iter()
-                        .position(|u| u.unspent_hash == self.unspent_hash);
-
-                    if let Some(i) = index {
-                        let rm_utxo = person.utxos.remove(i);
-                        // Update command to remove UTXO from database
-                        let update =
-                            doc! {"$pull": {"utxos": {"unspent_hash": rm_utxo.unspent_hash}}};
+impl Canonical for UTXO {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.block.encode(out);
+        self.trx_hash.encode(out);
+        self.output_hash.encode(out);
+        self.unspent_hash.encode(out);
+        self.unspent.encode(out);
+    }
+}
 
-                        // Update the collection
-                        match collection
-                            .update_one(doc! {"wallet": wallet.to_string()}, update)
-                            .await
-                        {
-                            Ok(_) => Ok(()),
-                            Err(_) => Err("Error during the updating of utxos-(tools/utxo 67)"),
-                        }
-                    } else {
-                        Err("UTXO does not exist!")
-                    }
-                }
-                None => Err("UTXO does not exist!"),
-            },
-            Err(_) => Err("Problem from query of utxos collection-(tools/utxo 75)"),
+impl UTXO {
+    // Check if a UTXO exists and is not spent in the change set, then stage its spending
+    pub async fn check<'a, S: Storage>(
+        &self,
+        db: &S,
+        wallet: &Public,
+        changes: &mut ChangeSet,
+    ) -> Result<(), Invalid<'a>> {
+        match db.find_utxo(wallet, &self.unspent_hash).await {
+            // Value of the input must be the stored one, so amounts of the transaction are real
+            Ok(Some(utxo))
+                if utxo.unspent == self.unspent && !changes.is_spent(&self.unspent_hash) =>
+            {
+                Ok(changes.spend(wallet, &utxo))
+            }
+            Ok(_) => Err(Invalid::Rejected("UTXO does not exist!")),
+            Err(e) => Err(Invalid::Storage(e)),
         }
     }
 
-    // Insert outputs of a transaction as UTXO
-    pub async fn generate<'a>(
+    // Stage outputs of a transaction as new UTXO
+    pub fn generate(
         block: u64,
         trx_hash: &String,
         output_hash: &String,
         unspent: &Unspent,
-        db: &'a Database,
-        wallet: &Public,
-        window: &tauri::Window,
-    ) -> Result<(), &'a str> {
-        let utxo_wallet = unspent.data.wallet;
+        changes: &mut ChangeSet,
+    ) {
         // Create new UTXO
         let utxo = Self {
             block: block,
@@ -96,53 +74,8 @@ impl UTXO {
             unspent_hash: unspent.hash.to_string(),
             unspent: unspent.data.value,
         };
-        let collection: Collection<Document> = db.collection("UTXOs");
-        let query = collection
-            .find_one(doc! {"wallet": utxo_wallet.to_string()})
-            .await;
-        match query {
-            Ok(opt) => {
-                if let Some(doc) = opt {
-                    // Update existing person's UTXOs
-                    let utxo_to_doc = to_document(&utxo).unwrap();
-                    let update = doc! {"$push": {"utxos": utxo_to_doc}};
-                    match collection.update_one(doc, update).await {
-                        Ok(_) => {
-                            if utxo_wallet == *wallet {
-                                // Update sum of centies for the wallet
-                                let sum_centies = Transactions::sum_centies(db, wallet).await;
-                                match sum_centies {
-                                    Ok(sum) => Ok(window.emit("sum_centies", sum).unwrap()),
-                                    Err(e) => Err(e),
-                                }
-                            } else {
-                                Ok(())
-                            }
-                        }
-                        Err(_) => Err("Error while updating utxos-(tools/utxo 121)"),
-                    }
-                } else {
-                    // Create new person with UTXO
-                    let new_person = Person::new(utxo_wallet, vec![utxo]);
-                    let person_to_doc = to_document(&new_person).unwrap();
-                    match collection.insert_one(person_to_doc).await {
-                        Ok(_) => {
-                            if utxo_wallet == *wallet {
-                                // Update sum of centies for the wallet
-                                let sum_centies = Transactions::sum_centies(db, wallet).await;
-                                match sum_centies {
-                                    Ok(sum) => Ok(window.emit("sum_centies", sum).unwrap()),
-                                    Err(e) => Err(e),
-                                }
-                            } else {
-                                Ok(())
-                            }
-                        }
-                        Err(_) => Err("Error while inserting utxos-(tools/utxo 140)"),
-                    }
-                }
-            }
-            Err(_) => Err("Error during query of mongodb-(tools/utxo 144)"),
-        }
+
+        // UTXO will be pushed to the person's UTXOs when the change set is committed
+        changes.create(&unspent.data.wallet, utxo);
     }
 }
@@ -1,85 +1,57 @@
-use libp2p::{futures::StreamExt, PeerId};
-use mongodb::{
-    bson::{doc, from_document, to_document, Document},
-    Collection, Database,
-};
-
-use crate::generator::validator::Validator;
+use libp2p::PeerId;
 
-use super::trun_sync::{Sync, Turn};
+use super::{
+    changeset::ChangeSet,
+    storage::Storage,
+    trun_sync::{Sync, Turn},
+};
 
 pub struct Waiting;
 
 impl Waiting {
-    pub async fn update<'a>(
-        db: &'a Database,
+    pub async fn update<'a, S: Storage>(
+        db: &'a S,
         block_generator: &PeerId,
         turn: &mut Turn,
         window: &tauri::Window,
         sync_state: &mut Sync,
+        changes: &mut ChangeSet,
     ) -> Result<(), &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        let query = collection.find(doc! {}).await;
-        match query {
-            Ok(mut cursor) => {
-                let mut is_err = None;
-                while let Some(Ok(doc)) = cursor.next().await {
-                    let mut validator: Validator = from_document(doc.clone()).unwrap();
+        match db.validators().await {
+            Ok(validators) => {
+                let count = validators.len() as u64;
+                for previous in validators {
+                    let mut validator = previous.clone();
                     // Check if the validator is the block generator
                     if &validator.peerid == block_generator {
                         // Set waiting to the total number of validators
-                        validator.waiting =
-                            collection.count_documents(doc! {}).await.unwrap() as u64;
-                        let replacement = to_document(&validator).unwrap();
-                        match collection.replace_one(doc, replacement).await {
-                            Ok(_) => {}
-                            Err(_) => {
-                                is_err.get_or_insert(
-                                    "Error during the replacing of document-(tools/waiting 30)",
-                                );
-                                break;
-                            }
-                        }
+                        validator.waiting = count;
+                        changes.update_validator(&previous, validator);
                     } else {
                         // Decrease waiting by 1 if it's greater than 0
                         if validator.waiting > 0 {
                             validator.waiting -= 1;
-                            let replacement = to_document(&validator).unwrap();
-                            match collection.replace_one(doc, replacement).await {
-                                Ok(_) => {}
-                                Err(_) => {
-                                    is_err.get_or_insert(
-                                        "Error during the replacing of document-(tools/waiting 44)",
-                                    );
-                                    break;
-                                }
-                            }
+                            changes.update_validator(&previous, validator);
                         }
                     }
                 }
-                if is_err.is_none() {
-                    match sync_state {
-                        Sync::Synced => {
-                            // Update turn waiting if it's greater than 0
-                            if turn.waiting > 0 {
-                                turn.waiting_update(window);
-                            }
+                match sync_state {
+                    Sync::Synced => {
+                        // Update turn waiting if it's greater than 0
+                        if turn.waiting > 0 {
+                            turn.waiting_update(window);
                         }
-                        _ => {}
                     }
-                    Ok(())
-                } else {
-                    Err(is_err.unwrap())
+                    _ => {}
                 }
+                Ok(())
             }
-            Err(_) => Err("Error during quering in-(tools/waiting 58)"),
+            Err(e) => Err(e),
         }
     }
 
-    pub async fn new<'a>(db: &'a Database) -> Result<u64, &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        let coun_query = collection.count_documents(doc! {}).await;
-        match coun_query {
+    pub async fn new<'a, S: Storage>(db: &'a S) -> Result<u64, &'a str> {
+        match db.validators_count().await {
             Ok(count) => {
                 if count > 0 {
                     // Set initial waiting to twice the number of validators
@@ -88,7 +60,7 @@ impl Waiting {
                     Ok(0)
                 }
             }
-            Err(_) => Err("Error while get count of validators-(relay/tools/waiting 73)"),
+            Err(e) => Err(e),
         }
     }
 }
@@ -1,58 +1,61 @@
 use libp2p::PeerId;
-use mongodb::{
-    bson::{doc, Document},
-    Collection, Database,
-};
 use serde::{Deserialize, Serialize};
 
 use super::{
+    changeset::ChangeSet,
+    storage::Storage,
     trun_sync::{Sync, Turn},
     waiting::Waiting,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WrongDoer {
-    peerid: PeerId,
-    cause: String,
+    pub peerid: PeerId,
+    pub cause: String,
 }
 
 impl WrongDoer {
-    pub async fn remove<'a>(
-        db: &'a Database,
+    pub fn new(peerid: PeerId, cause: String) -> Self {
+        Self { peerid, cause }
+    }
+
+    pub async fn remove<'a, S: Storage>(
+        db: &'a S,
         peerid: PeerId,
         turn: &mut Turn,
         sync_state: &mut Sync,
         window: &tauri::Window,
     ) -> Result<PeerId, &'a str> {
-        let collection: Collection<Document> = db.collection("validators");
-        let filter = doc! {"$or": [{"peerid": peerid.to_string()}, {"relay": peerid.to_string()}]}; // filter validators who their peerid is wrongdoer or its rela
-
-        //get count of documents that will be removed for update validators' waiting
-        match collection.count_documents(filter.clone()).await {
-            //deleting validators
-            Ok(mut count) => match collection.delete_many(filter).await {
-                Ok(_) => {
-                    let mut is_err = None;
-                    while count > 0 {
-                        //validators' waiting update for each deleted validator
-                        match Waiting::update(db, &peerid, turn, window, sync_state).await {
-                            Ok(_) => count -= 1,
-                            Err(e) => {
-                                is_err.get_or_insert(e);
-                                break;
-                            }
+        // deleting the validator of the wrongdoer and get count of deleted for update validators' waiting
+        match db.remove_validators(&peerid).await {
+            Ok(mut count) => {
+                let mut is_err = None;
+                while count > 0 {
+                    //validators' waiting update for each deleted validator
+                    let mut changes = ChangeSet::new();
+                    let update =
+                        match Waiting::update(db, &peerid, turn, window, sync_state, &mut changes)
+                            .await
+                        {
+                            Ok(_) => db.commit(None, &changes).await,
+                            Err(e) => Err(e),
+                        };
+                    match update {
+                        Ok(_) => count -= 1,
+                        Err(e) => {
+                            is_err.get_or_insert(e);
+                            break;
                         }
                     }
+                }
 
-                    // if there is no any errors, return peerid
-                    match is_err {
-                        None => Ok(peerid),
-                        Some(e) => Err(e),
-                    }
+                // if there is no any errors, return peerid
+                match is_err {
+                    None => Ok(peerid),
+                    Some(e) => Err(e),
                 }
-                Err(_) => Err("Deleting validators from mongodb has problem-(tools/wrongdoer 44)"),
-            },
-            Err(_) => Err("counting validators from mongodb has problem-(tools/wrongdoer 46)"),
+            }
+            Err(e) => Err(e),
         }
     }
 }